@@ -0,0 +1,544 @@
+use crate::{data::SensorValues, units::Unit};
+use gio::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
+use uuid::Uuid;
+
+/// An alert rule for a single sensor, all values are in raw sensor units.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub sensor: Uuid,
+    pub kind: RuleKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum RuleKind {
+    /// Fires above `limit` and recovers once the value fell below `limit - hysteresis`.
+    Above {
+        unit: Unit,
+        limit: f64,
+        hysteresis: f64,
+    },
+    /// Fires below `limit` and recovers once the value rose above `limit + hysteresis`.
+    Below {
+        unit: Unit,
+        limit: f64,
+        hysteresis: f64,
+    },
+    /// Fires when the value changed by at least `change` within the last `minutes`.
+    RateOfChange {
+        unit: Unit,
+        change: f64,
+        minutes: u32,
+    },
+    /// Fires when the sensor has been disconnected for at least `minutes`.
+    Disconnected { minutes: u32 },
+}
+
+impl RuleKind {
+    pub fn describe(&self) -> String {
+        match *self {
+            RuleKind::Above {
+                unit,
+                limit,
+                hysteresis,
+            } => format!(
+                "{} above {} (hysteresis {})",
+                unit.label(),
+                unit.format_value(limit),
+                unit.format_delta(hysteresis)
+            ),
+            RuleKind::Below {
+                unit,
+                limit,
+                hysteresis,
+            } => format!(
+                "{} below {} (hysteresis {})",
+                unit.label(),
+                unit.format_value(limit),
+                unit.format_delta(hysteresis)
+            ),
+            RuleKind::RateOfChange {
+                unit,
+                change,
+                minutes,
+            } => format!(
+                "{} changes by {} within {} min",
+                unit.label(),
+                unit.format_delta(change),
+                minutes
+            ),
+            RuleKind::Disconnected { minutes } => {
+                format!("Disconnected for {} min", minutes)
+            }
+        }
+    }
+
+    pub fn unit(&self) -> Option<Unit> {
+        match *self {
+            RuleKind::Above { unit, .. }
+            | RuleKind::Below { unit, .. }
+            | RuleKind::RateOfChange { unit, .. } => Some(unit),
+            RuleKind::Disconnected { .. } => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transition {
+    Fired,
+    Recovered,
+}
+
+/// A rule changing from ok to firing or back.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AlertEvent {
+    pub time: u32,
+    pub rule: Rule,
+    pub transition: Transition,
+    /// Value of the rule's unit that caused the transition
+    pub value: Option<f64>,
+}
+
+#[derive(Default)]
+struct RuleState {
+    firing: bool,
+    samples: VecDeque<(u32, f64)>,
+    disconnected_since: Option<u32>,
+}
+
+/// Evaluates the configured [`Rule`]s against incoming sensor values.
+#[derive(Default)]
+pub struct AlertEngine {
+    rules: Vec<(Rule, RuleState)>,
+}
+
+impl AlertEngine {
+    /// Replace the rules, rules that didn't change keep their state.
+    pub fn set_rules(&mut self, rules: Vec<Rule>) {
+        let mut old = std::mem::take(&mut self.rules);
+        self.rules = rules
+            .into_iter()
+            .map(|rule| match old.iter().position(|(old, _)| *old == rule) {
+                Some(i) => old.swap_remove(i),
+                None => (rule, RuleState::default()),
+            })
+            .collect();
+    }
+
+    /// Forget the state of the rules of `sensor`, e.g. once it's hidden and
+    /// no longer evaluated, returns the rules that were firing.
+    pub fn clear(&mut self, sensor: Uuid) -> Vec<Rule> {
+        self.rules
+            .iter_mut()
+            .filter(|(rule, _)| rule.sensor == sensor)
+            .filter_map(|(rule, state)| std::mem::take(state).firing.then(|| rule.clone()))
+            .collect()
+    }
+
+    /// Feed the current values of `sensor`, `None` if it's disconnected.
+    pub fn evaluate(
+        &mut self,
+        sensor: Uuid,
+        values: Option<SensorValues>,
+        now: u32,
+    ) -> Vec<AlertEvent> {
+        let mut ret = Vec::new();
+        for (rule, state) in self
+            .rules
+            .iter_mut()
+            .filter(|(rule, _)| rule.sensor == sensor)
+        {
+            let (firing, value) = match (rule.kind, values) {
+                (
+                    RuleKind::Above {
+                        unit,
+                        limit,
+                        hysteresis,
+                    },
+                    Some(values),
+                ) => {
+                    let value = values.get(unit);
+                    let firing = if state.firing {
+                        value >= limit - hysteresis
+                    } else {
+                        value > limit
+                    };
+                    (firing, Some(value))
+                }
+                (
+                    RuleKind::Below {
+                        unit,
+                        limit,
+                        hysteresis,
+                    },
+                    Some(values),
+                ) => {
+                    let value = values.get(unit);
+                    let firing = if state.firing {
+                        value <= limit + hysteresis
+                    } else {
+                        value < limit
+                    };
+                    (firing, Some(value))
+                }
+                (
+                    RuleKind::RateOfChange {
+                        unit,
+                        change,
+                        minutes,
+                    },
+                    Some(values),
+                ) => {
+                    let value = values.get(unit);
+                    let window_start = now.saturating_sub(minutes * 60);
+                    while matches!(state.samples.front(), Some((time, _)) if *time < window_start) {
+                        state.samples.pop_front();
+                    }
+                    state.samples.push_back((now, value));
+                    let (min, max) = state
+                        .samples
+                        .iter()
+                        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (_, v)| {
+                            (min.min(*v), max.max(*v))
+                        });
+                    (max - min >= change, Some(value))
+                }
+                (RuleKind::Disconnected { minutes }, None) => {
+                    let since = *state.disconnected_since.get_or_insert(now);
+                    (now.saturating_sub(since) >= minutes * 60, None)
+                }
+                (RuleKind::Disconnected { .. }, Some(_)) => {
+                    state.disconnected_since = None;
+                    (false, None)
+                }
+                // no values, keep threshold and rate rules in their current state
+                (_, None) => continue,
+            };
+
+            if firing != state.firing {
+                state.firing = firing;
+                ret.push(AlertEvent {
+                    time: now,
+                    rule: rule.clone(),
+                    transition: if firing {
+                        Transition::Fired
+                    } else {
+                        Transition::Recovered
+                    },
+                    value,
+                });
+            }
+        }
+        ret
+    }
+}
+
+/// An [`AlertEvent`] as shown in the alert history.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Label of the sensor at the time of the event
+    pub sensor_label: String,
+    #[serde(flatten)]
+    pub event: AlertEvent,
+}
+
+/// Past [`AlertEvent`]s, oldest first, saved as json in the user data dir.
+pub struct AlertHistory {
+    path: PathBuf,
+    entries: Vec<HistoryEntry>,
+}
+
+impl AlertHistory {
+    /// Only the newest entries are kept.
+    const MAX_ENTRIES: usize = 1000;
+
+    pub fn load() -> Self {
+        let path = glib::user_data_dir()
+            .join("ble-ws-ui")
+            .join("alert-history.json");
+        let entries = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content).unwrap_or_else(|e| {
+                tracing::error!("Invalid alert history in {}: {}", path.display(), e);
+                Default::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Default::default(),
+            Err(e) => {
+                tracing::error!("Could not read {}: {}", path.display(), e);
+                Default::default()
+            }
+        };
+
+        Self { path, entries }
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+        if self.entries.len() > Self::MAX_ENTRIES {
+            let excess = self.entries.len() - Self::MAX_ENTRIES;
+            self.entries.drain(..excess);
+        }
+        if let Err(e) = self.save() {
+            tracing::error!("Failed saving alert history: {}", e);
+        }
+    }
+
+    fn save(&self) -> Result<(), anyhow::Error> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.entries)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Rules configured in the preferences, saved as json in GSettings.
+pub fn load_rules(settings: &gio::Settings) -> Vec<Rule> {
+    let rules = settings.get::<String>("alert-rules");
+    if rules.is_empty() {
+        return Vec::new();
+    }
+    serde_json::from_str(&rules).unwrap_or_else(|e| {
+        tracing::error!("Invalid alert rules in settings: {}", e);
+        Vec::new()
+    })
+}
+
+pub fn save_rules(settings: &gio::Settings, rules: &[Rule]) -> Result<(), anyhow::Error> {
+    settings.set("alert-rules", &serde_json::to_string(rules)?)?;
+    Ok(())
+}
+
+/// Show `alert` as a desktop notification, a recovery replaces the notification of its firing.
+pub fn send_notification(app: &gio::Application, sensor_label: &str, alert: &AlertEvent) {
+    let title = match alert.transition {
+        Transition::Fired => format!("Alert: {}", sensor_label),
+        Transition::Recovered => format!("Recovered: {}", sensor_label),
+    };
+    let mut body = alert.rule.kind.describe();
+    if let (Some(unit), Some(value)) = (alert.rule.kind.unit(), alert.value) {
+        body.push_str(&format!(", currently {}", unit.format_value(value)));
+    }
+
+    let notification = gio::Notification::new(&title);
+    notification.set_body(Some(&body));
+    if alert.transition == Transition::Fired {
+        notification.set_priority(gio::NotificationPriority::High);
+    }
+    app.send_notification(Some(&notification_id(&alert.rule)), &notification);
+}
+
+/// Remove the notification of a firing `rule` that won't recover anymore.
+pub fn withdraw_notification(app: &gio::Application, rule: &Rule) {
+    app.withdraw_notification(&notification_id(rule));
+}
+
+/// Id of the notification of `rule`, independent of the unit system so a
+/// recovery still replaces the notification after the units changed.
+fn notification_id(rule: &Rule) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(&rule.kind)
+        .expect("rule kinds always serialize")
+        .hash(&mut hasher);
+    format!("alert-{}-{:016x}", rule.sensor, hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Corrections;
+
+    fn sensor() -> Uuid {
+        Uuid::from_u128(1)
+    }
+
+    fn values(temperature: i32) -> Option<SensorValues> {
+        Some(SensorValues {
+            temperature,
+            humidity: 5000,
+            pressure: 1_013_250,
+            corrections: Corrections::default(),
+        })
+    }
+
+    fn engine(kinds: &[RuleKind]) -> AlertEngine {
+        let mut engine = AlertEngine::default();
+        engine.set_rules(rules(kinds));
+        engine
+    }
+
+    fn rules(kinds: &[RuleKind]) -> Vec<Rule> {
+        kinds
+            .iter()
+            .map(|&kind| Rule {
+                sensor: sensor(),
+                kind,
+            })
+            .collect()
+    }
+
+    /// Transitions after feeding `temperature` at `now`.
+    fn feed(engine: &mut AlertEngine, temperature: Option<i32>, now: u32) -> Vec<Transition> {
+        engine
+            .evaluate(sensor(), temperature.and_then(values), now)
+            .into_iter()
+            .map(|event| event.transition)
+            .collect()
+    }
+
+    #[test]
+    fn above() {
+        let mut engine = engine(&[RuleKind::Above {
+            unit: Unit::Temperature,
+            limit: 2500.,
+            hysteresis: 100.,
+        }]);
+        assert_eq!(feed(&mut engine, Some(2500), 0), vec![]);
+        assert_eq!(feed(&mut engine, Some(2501), 60), vec![Transition::Fired]);
+        // still firing within the hysteresis
+        assert_eq!(feed(&mut engine, Some(2450), 120), vec![]);
+        assert_eq!(feed(&mut engine, Some(2400), 180), vec![]);
+        // a disconnect keeps the state
+        assert_eq!(feed(&mut engine, None, 240), vec![]);
+        assert_eq!(
+            feed(&mut engine, Some(2399), 300),
+            vec![Transition::Recovered]
+        );
+        assert_eq!(feed(&mut engine, Some(2450), 360), vec![]);
+    }
+
+    #[test]
+    fn below() {
+        let mut engine = engine(&[RuleKind::Below {
+            unit: Unit::Temperature,
+            limit: 500.,
+            hysteresis: 100.,
+        }]);
+        assert_eq!(feed(&mut engine, Some(500), 0), vec![]);
+        assert_eq!(feed(&mut engine, Some(-100), 60), vec![Transition::Fired]);
+        assert_eq!(feed(&mut engine, Some(600), 120), vec![]);
+        assert_eq!(
+            feed(&mut engine, Some(601), 180),
+            vec![Transition::Recovered]
+        );
+        assert_eq!(feed(&mut engine, Some(550), 240), vec![]);
+    }
+
+    #[test]
+    fn rate_of_change() {
+        let mut engine = engine(&[RuleKind::RateOfChange {
+            unit: Unit::Temperature,
+            change: 200.,
+            minutes: 10,
+        }]);
+        assert_eq!(feed(&mut engine, Some(2000), 0), vec![]);
+        assert_eq!(feed(&mut engine, Some(2100), 300), vec![]);
+        assert_eq!(feed(&mut engine, Some(2200), 600), vec![Transition::Fired]);
+        // the sample at 0 left the window
+        assert_eq!(
+            feed(&mut engine, Some(2200), 601),
+            vec![Transition::Recovered]
+        );
+        assert_eq!(feed(&mut engine, Some(2200), 901), vec![]);
+        // the samples up to 300 left the window, the one at 601 is still in it
+        assert_eq!(feed(&mut engine, Some(2000), 1201), vec![Transition::Fired]);
+    }
+
+    #[test]
+    fn disconnected() {
+        let mut engine = engine(&[RuleKind::Disconnected { minutes: 5 }]);
+        assert_eq!(feed(&mut engine, Some(2000), 0), vec![]);
+        assert_eq!(feed(&mut engine, None, 60), vec![]);
+        assert_eq!(feed(&mut engine, None, 359), vec![]);
+        assert_eq!(feed(&mut engine, None, 360), vec![Transition::Fired]);
+        assert_eq!(feed(&mut engine, None, 420), vec![]);
+        assert_eq!(
+            feed(&mut engine, Some(2000), 480),
+            vec![Transition::Recovered]
+        );
+        // the reconnect restarted the timer
+        assert_eq!(feed(&mut engine, None, 540), vec![]);
+        assert_eq!(feed(&mut engine, None, 780), vec![]);
+        assert_eq!(feed(&mut engine, None, 840), vec![Transition::Fired]);
+    }
+
+    #[test]
+    fn set_rules_keeps_state() {
+        let above = RuleKind::Above {
+            unit: Unit::Temperature,
+            limit: 2500.,
+            hysteresis: 0.,
+        };
+        let below = RuleKind::Below {
+            unit: Unit::Temperature,
+            limit: 2800.,
+            hysteresis: 0.,
+        };
+        let mut engine = engine(&[above, below]);
+        assert_eq!(
+            feed(&mut engine, Some(2600), 0),
+            vec![Transition::Fired, Transition::Fired]
+        );
+
+        // the changed rule starts over and fires again, the unchanged one doesn't
+        let changed = RuleKind::Below {
+            unit: Unit::Temperature,
+            limit: 2700.,
+            hysteresis: 0.,
+        };
+        engine.set_rules(rules(&[changed, above]));
+        let events = engine.evaluate(sensor(), values(2600), 60);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].rule.kind, changed);
+        assert_eq!(events[0].transition, Transition::Fired);
+    }
+
+    #[test]
+    fn clear() {
+        let above = RuleKind::Above {
+            unit: Unit::Temperature,
+            limit: 2500.,
+            hysteresis: 0.,
+        };
+        let below = RuleKind::Below {
+            unit: Unit::Temperature,
+            limit: 0.,
+            hysteresis: 0.,
+        };
+        let mut engine = engine(&[above, below]);
+        feed(&mut engine, Some(2600), 0);
+
+        let firing = engine.clear(sensor());
+        assert_eq!(firing, rules(&[above]));
+        assert!(engine.clear(sensor()).is_empty());
+        // the state started over, the rule fires again
+        assert_eq!(feed(&mut engine, Some(2600), 60), vec![Transition::Fired]);
+    }
+
+    #[test]
+    fn notification_ids() {
+        let rule = &rules(&[RuleKind::Above {
+            unit: Unit::Temperature,
+            limit: 2500.,
+            hysteresis: 100.,
+        }])[0];
+        let id = notification_id(rule);
+        assert_eq!(id, notification_id(&rule.clone()));
+        assert_ne!(
+            id,
+            notification_id(&rules(&[RuleKind::Disconnected { minutes: 5 }])[0])
+        );
+    }
+}
@@ -1,17 +1,42 @@
 use crate::config::APP_ID;
-use gtk::prelude::*;
+use gtk::{prelude::*, subclass::prelude::*};
 
 mod imp {
-    use crate::event_loop::Event;
+    use crate::{
+        deep_link::{DeepLink, SensorRef},
+        event_loop::Event,
+    };
 
     use super::*;
     use glib::WeakRef;
     use gtk::subclass::prelude::*;
     use once_cell::sync::OnceCell;
+    use std::{
+        cell::{Cell, RefCell},
+        path::PathBuf,
+    };
 
-    #[derive(Debug, Default)]
+    #[derive(Debug)]
     pub struct BleWsGtk {
         window: OnceCell<WeakRef<crate::widgets::Window>>,
+        tx: OnceCell<glib::Sender<Event>>,
+        /// whether the app is held while its window is hidden
+        pub held: Cell<bool>,
+        pub settings: gio::Settings,
+        /// open import or export dialog, destroyed when dropped
+        file_dialog: RefCell<Option<gtk::FileChooserNative>>,
+    }
+
+    impl Default for BleWsGtk {
+        fn default() -> Self {
+            Self {
+                window: Default::default(),
+                tx: Default::default(),
+                held: Default::default(),
+                settings: crate::config::settings(),
+                file_dialog: Default::default(),
+            }
+        }
     }
 
     #[glib::object_subclass]
@@ -26,6 +51,23 @@ mod imp {
         fn startup(&self, app: &Self::Type) {
             self.parent_startup(app);
             adw::init();
+            crate::units::UnitSystem::from_settings(&self.settings).set_current();
+
+            self.settings
+                .connect_changed(Some("autostart"), |settings, key| {
+                    let enabled = settings.boolean(key);
+                    glib::MainContext::default().spawn_local(async move {
+                        if let Err(e) = crate::autostart::set_autostart(enabled).await {
+                            tracing::error!("Failed changing autostart: {}", e);
+                        }
+                    });
+                });
+
+            // started at login, only run in the background until activated
+            if app.flags().contains(gio::ApplicationFlags::IS_SERVICE) {
+                self.create_window(app);
+                app.run_in_background();
+            }
         }
 
         fn activate(&self, app: &Self::Type) {
@@ -33,10 +75,72 @@ mod imp {
             // the below block just raises the already open instead of creating a new window
             if let Some(window) = self.window.get() {
                 let window = window.upgrade().unwrap();
+                if self.held.replace(false) {
+                    app.release();
+                }
                 window.present();
                 return;
             }
 
+            self.create_window(app).show();
+        }
+
+        fn command_line(&self, app: &Self::Type, cmdline: &gio::ApplicationCommandLine) -> i32 {
+            let options = cmdline.options_dict();
+            let option = |name: &str| {
+                options
+                    .lookup_value(name, Some(glib::VariantTy::STRING))
+                    .and_then(|value| value.get::<String>())
+            };
+
+            let mut links = Vec::new();
+            if let Some(sensor) = option("sensor") {
+                links.push(DeepLink::new(
+                    SensorRef::parse(&sensor),
+                    option("from").as_deref(),
+                    option("to").as_deref(),
+                    option("unit").as_deref(),
+                ));
+            }
+            // the first argument is the program name
+            for arg in cmdline.arguments().iter().skip(1) {
+                links.push(DeepLink::parse_uri(&arg.to_string_lossy()));
+            }
+            let links = match links.into_iter().collect::<Result<Vec<_>, _>>() {
+                Ok(links) => links,
+                Err(e) => {
+                    cmdline.printerr_literal(&format!("{}\n", e));
+                    return 1;
+                }
+            };
+
+            app.activate();
+            for link in links {
+                self.open_link(link);
+            }
+            0
+        }
+
+        fn open(&self, app: &Self::Type, files: &[gio::File], _hint: &str) {
+            app.activate();
+            for file in files {
+                match DeepLink::parse_uri(&file.uri()) {
+                    Ok(link) => self.open_link(link),
+                    Err(e) => tracing::error!("Can't open {}: {}", file.uri(), e),
+                }
+            }
+        }
+    }
+    impl GtkApplicationImpl for BleWsGtk {}
+
+    impl BleWsGtk {
+        fn open_link(&self, link: DeepLink) {
+            if let Some(tx) = self.tx.get() {
+                tx.send(Event::OpenLink(link)).unwrap();
+            }
+        }
+
+        fn create_window(&self, app: &super::BleWsGtk) -> crate::widgets::Window {
             if let Some(ref display) = gtk::gdk::Display::default() {
                 let p = gtk::CssProvider::new();
                 gtk::CssProvider::load_from_resource(&p, "/li/_5kw/BleWsGtk/style.css");
@@ -53,6 +157,9 @@ mod imp {
                 glib::clone!(@weak app  => move |_, _| {
                     let active_window = app.active_window().unwrap();
                     let preferences = crate::widgets::PreferencesWindow::new();
+                    if let Some(window) = active_window.downcast_ref::<crate::widgets::Window>() {
+                        preferences.set_sensors(&window.sensors());
+                    }
                     preferences.set_transient_for(Some(&active_window));
                     preferences.show();
                 })
@@ -66,19 +173,101 @@ mod imp {
                 })
             );
 
+            gtk_macros::action!(
+                app,
+                "import-metadata",
+                glib::clone!(@weak app, @strong tx => move |_, _| {
+                    let self_ = BleWsGtk::from_instance(&app);
+                    self_.choose_metadata_file(
+                        &app,
+                        gtk::FileChooserAction::Open,
+                        glib::clone!(@strong tx => move |path| {
+                            tx.send(Event::ImportMetadata(path)).unwrap();
+                        }),
+                    );
+                })
+            );
+
+            gtk_macros::action!(
+                app,
+                "export-metadata",
+                glib::clone!(@weak app, @strong tx => move |_, _| {
+                    let self_ = BleWsGtk::from_instance(&app);
+                    self_.choose_metadata_file(
+                        &app,
+                        gtk::FileChooserAction::Save,
+                        glib::clone!(@strong tx => move |path| {
+                            tx.send(Event::ExportMetadata(path)).unwrap();
+                        }),
+                    );
+                })
+            );
+
+            gtk_macros::action!(
+                app,
+                "quit",
+                glib::clone!(@weak app => move |_, _| {
+                    app.quit();
+                })
+            );
+
             app.set_accels_for_action("app.preferences", &["<primary>p"]);
+            app.set_accels_for_action("app.quit", &["<primary>q"]);
             app.set_accels_for_action("app.search", &["<primary>f"]);
             app.set_accels_for_action("win.show-help-overlay", &["<primary>question"]);
 
             let window = crate::widgets::Window::connect(app, tx.clone());
             self.window.set(window.downgrade()).unwrap();
+            self.tx.set(tx.clone()).unwrap();
 
             crate::event_loop::attach(tx, rx, window.clone());
 
-            window.show();
+            window
+        }
+
+        /// Ask for the json file to import the sensor metadata from or
+        /// export it to and pass its path to `f`.
+        fn choose_metadata_file<F>(
+            &self,
+            app: &super::BleWsGtk,
+            action: gtk::FileChooserAction,
+            f: F,
+        ) where
+            F: Fn(PathBuf) + 'static,
+        {
+            let (title, accept) = match action {
+                gtk::FileChooserAction::Save => ("Export sensor metadata", "_Export"),
+                _ => ("Import sensor metadata", "_Import"),
+            };
+            let dialog = gtk::FileChooserNative::new(
+                Some(title),
+                app.active_window().as_ref(),
+                action,
+                Some(accept),
+                Some("_Cancel"),
+            );
+            let filter = gtk::FileFilter::new();
+            filter.set_name(Some("JSON file"));
+            filter.add_pattern("*.json");
+            dialog.add_filter(&filter);
+            if action == gtk::FileChooserAction::Save {
+                dialog.set_current_name("sensors.json");
+            }
+
+            dialog.connect_response(glib::clone!(@weak app => move |dialog, response| {
+                if response == gtk::ResponseType::Accept {
+                    if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                        f(path);
+                    }
+                }
+                let self_ = BleWsGtk::from_instance(&app);
+                self_.file_dialog.replace(None);
+            }));
+
+            dialog.show();
+            self.file_dialog.replace(Some(dialog));
         }
     }
-    impl GtkApplicationImpl for BleWsGtk {}
 }
 
 glib::wrapper! {
@@ -95,11 +284,43 @@ impl BleWsGtk {
     pub fn new() -> Self {
         let app: Self = glib::Object::new(&[
             ("application-id", &Some(APP_ID)),
-            ("flags", &gio::ApplicationFlags::empty()),
+            (
+                "flags",
+                &(gio::ApplicationFlags::HANDLES_COMMAND_LINE
+                    | gio::ApplicationFlags::HANDLES_OPEN),
+            ),
             ("resource-base-path", &Some("/li/_5kw/BleWsGtk")),
         ])
         .expect("Failed to create application instance");
 
+        for (name, description, arg_description) in &[
+            ("sensor", "Open the sensor with this id or label", "SENSOR"),
+            (
+                "from",
+                "Start of the range to show, a unix timestamp, YYYY-MM-DD or \"YYYY-MM-DD HH:MM\"",
+                "TIME",
+            ),
+            ("to", "End of the range to show", "TIME"),
+            ("unit", "Page to show, e.g. temperature or dewpoint", "UNIT"),
+        ] {
+            app.add_main_option(
+                name,
+                glib::Char(0),
+                glib::OptionFlags::NONE,
+                glib::OptionArg::String,
+                description,
+                Some(arg_description),
+            );
+        }
+
         app
     }
+
+    /// Keep running without a visible window until the next activation.
+    pub fn run_in_background(&self) {
+        let self_ = imp::BleWsGtk::from_instance(self);
+        if !self_.held.replace(true) {
+            self.hold();
+        }
+    }
 }
@@ -0,0 +1,95 @@
+use crate::config::APP_ID;
+use gio::prelude::*;
+use glib::ToVariant;
+use std::path::PathBuf;
+
+/// Arguments the app gets started with at login, it stays hidden in the background.
+const BACKGROUND_ARG: &str = "--gapplication-service";
+
+/// Start the app at login or stop doing so.
+///
+/// Uses the Background portal and falls back to a desktop file in the
+/// autostart directory if no portal is available.
+pub async fn set_autostart(enabled: bool) -> Result<(), anyhow::Error> {
+    match request_background(enabled).await {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            tracing::info!("Background portal unavailable, using autostart file: {}", e);
+            set_autostart_file(enabled)
+        }
+    }
+}
+
+fn commandline() -> Vec<String> {
+    let exe = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.to_str().map(ToOwned::to_owned))
+        .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_owned());
+    vec![exe, BACKGROUND_ARG.to_owned()]
+}
+
+async fn request_background(enabled: bool) -> Result<(), anyhow::Error> {
+    let connection = gio::bus_get_future(gio::BusType::Session).await?;
+    let options = glib::VariantDict::new(None);
+    options.insert_value(
+        "reason",
+        &"Notify about sensor alerts while the window is closed".to_variant(),
+    );
+    options.insert_value("autostart", &enabled.to_variant());
+    options.insert_value("commandline", &commandline().to_variant());
+    let parameters = glib::Variant::from_tuple(&["".to_variant(), options.end()]);
+
+    // the portal answers with a request handle, denial only shows up as
+    // a response on that, which we don't need since the setting stays as is
+    connection
+        .call_future(
+            Some("org.freedesktop.portal.Desktop"),
+            "/org/freedesktop/portal/desktop",
+            "org.freedesktop.portal.Background",
+            "RequestBackground",
+            Some(&parameters),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await?;
+    Ok(())
+}
+
+fn autostart_file() -> PathBuf {
+    glib::user_config_dir()
+        .join("autostart")
+        .join(format!("{}.desktop", APP_ID))
+}
+
+fn set_autostart_file(enabled: bool) -> Result<(), anyhow::Error> {
+    let path = autostart_file();
+    if enabled {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let exec = commandline()
+            .iter()
+            .map(|arg| glib::shell_quote(arg).to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        std::fs::write(
+            &path,
+            format!(
+                "[Desktop Entry]\n\
+                 Type=Application\n\
+                 Name=Ble Weatherstation\n\
+                 Exec={}\n\
+                 NoDisplay=true\n\
+                 X-GNOME-Autostart-enabled=true\n",
+                exec
+            ),
+        )?;
+    } else {
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => (),
+        }
+    }
+    Ok(())
+}
@@ -1,5 +1,13 @@
+use crate::{
+    deep_link::SensorRef,
+    forecast::Tendency,
+    local_store::{Calibrations, SensorSettings},
+    physics,
+    sensor_obj::SensorObj,
+    units::Unit,
+};
 use indexmap::IndexMap;
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, ops::Range, rc::Rc};
 use uuid::Uuid;
 
 #[derive(Clone, Copy)]
@@ -7,10 +15,146 @@ pub struct SensorValues {
     pub temperature: i32,
     pub humidity: u32,
     pub pressure: u32,
+    pub corrections: Corrections,
+}
+
+impl SensorValues {
+    pub fn get(&self, unit: Unit) -> f64 {
+        self.corrections.value(
+            unit,
+            f64::from(self.temperature),
+            f64::from(self.humidity),
+            f64::from(self.pressure),
+        )
+    }
+}
+
+/// Corrections applied to the raw measurements of a sensor before they are
+/// displayed or checked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, glib::GBoxed)]
+#[gboxed(type_name = "BleWsCorrections")]
+pub struct Corrections {
+    /// unset if the sensor has no calibration
+    pub calibration: Option<Calibrations>,
+    /// show the values as read by the sensor, without the calibration
+    pub raw_values: bool,
+    /// altitude in m and reference temperature in °C to reduce the pressure
+    /// to sea level with
+    pub sea_level: Option<(f64, Option<f64>)>,
+}
+
+impl Corrections {
+    pub fn new(settings: &SensorSettings, sea_level_pressure: bool, raw_values: bool) -> Self {
+        Self {
+            calibration: if settings.calibration.is_set() {
+                Some(settings.calibration)
+            } else {
+                None
+            },
+            raw_values,
+            sea_level: if sea_level_pressure {
+                Some((settings.altitude, settings.reference_temperature))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Tells whether the values are calibrated, `None` without a calibration.
+    pub fn calibration_label(&self) -> Option<&'static str> {
+        match self.calibration {
+            Some(_) if self.raw_values => Some("Raw values"),
+            Some(_) => Some("Calibrated"),
+            None => None,
+        }
+    }
+
+    /// Raw value of `unit` after correcting the raw measurements, the
+    /// calibration comes first.
+    pub fn value(&self, unit: Unit, temperature: f64, humidity: f64, pressure: f64) -> f64 {
+        let (temperature, humidity, pressure) = match self.calibration {
+            // an offset near saturation mustn't push the humidity past 100 %,
+            // the derived quantities aren't defined there
+            Some(calibration) if !self.raw_values => (
+                calibration.temperature.apply(temperature),
+                calibration
+                    .humidity
+                    .apply(humidity)
+                    .clamp(0., 100. * Unit::Humidity.scale()),
+                calibration.pressure.apply(pressure),
+            ),
+            _ => (temperature, humidity, pressure),
+        };
+        let pressure = match self.sea_level {
+            Some((altitude, reference_temperature)) => {
+                let scale = Unit::Pressure.scale();
+                physics::sea_level_pressure(pressure / scale, altitude, reference_temperature)
+                    * scale
+            }
+            None => pressure,
+        };
+        raw_value(unit, temperature, humidity, pressure)
+    }
+}
+
+/// Raw value of `unit` given the raw measurements, derived quantities are
+/// computed in the same scale as the measured ones.
+pub fn raw_value(unit: Unit, temperature: f64, humidity: f64, pressure: f64) -> f64 {
+    let celsius = temperature / Unit::Temperature.scale();
+    let humidity_percent = humidity / Unit::Humidity.scale();
+    let derived = match unit {
+        Unit::Temperature => return temperature,
+        Unit::Humidity => return humidity,
+        Unit::Pressure => return pressure,
+        Unit::DewPoint => physics::dew_point(celsius, humidity_percent),
+        Unit::AbsoluteHumidity => physics::absolute_humidity(celsius, humidity_percent),
+        Unit::HeatIndex => physics::heat_index(celsius, humidity_percent),
+        Unit::VapourPressureDeficit => physics::vapour_pressure_deficit(celsius, humidity_percent),
+    };
+    derived * unit.scale()
 }
 
 pub struct Data {
-    pub sensors: IndexMap<Uuid, crate::sensor_obj::SensorObj>,
+    pub sensors: IndexMap<Uuid, SensorObj>,
+}
+
+impl Data {
+    /// Sensor with the id or, failing that, the first one with the label,
+    /// else the first whose label starts with it ignoring the case.
+    pub fn find_sensor(&self, sensor: &SensorRef) -> Option<&SensorObj> {
+        match sensor {
+            SensorRef::Id(id) => self.sensors.get(id),
+            SensorRef::Label(label) => {
+                let labels = self
+                    .sensors
+                    .values()
+                    .map(|sensor| sensor.data().label.borrow().clone())
+                    .collect::<Vec<_>>();
+                find_label(&labels, label)
+                    .and_then(|index| self.sensors.get_index(index))
+                    .map(|(_, sensor)| sensor)
+            }
+        }
+    }
+}
+
+/// Index of the first of `labels` equal to `wanted` or, failing that, the
+/// first that starts with it ignoring the case.
+fn find_label(labels: &[Option<String>], wanted: &str) -> Option<usize> {
+    if wanted.is_empty() {
+        return None;
+    }
+    let wanted_lowercase = wanted.to_lowercase();
+    labels
+        .iter()
+        .position(|label| label.as_deref() == Some(wanted))
+        .or_else(|| {
+            labels.iter().position(|label| {
+                label.as_ref().map_or(false, |label| {
+                    label.to_lowercase().starts_with(&wanted_lowercase)
+                })
+            })
+        })
 }
 
 impl Default for Data {
@@ -82,6 +226,7 @@ pub struct Timeseries {
     temperature: Vec<i16>,
     humidity: Vec<u32>,
     pressure: Vec<u32>,
+    corrections: Corrections,
 }
 
 impl Timeseries {
@@ -109,6 +254,146 @@ impl Timeseries {
         self.pressure.as_slice()
     }
 
+    /// Correct the rows with `corrections` from now on, the stored rows
+    /// stay untouched.
+    pub fn set_corrections(&mut self, corrections: Corrections) {
+        self.corrections = corrections;
+    }
+
+    /// Get the values of the column displayed for `unit`.
+    pub fn column(&self, unit: Unit) -> Vec<f64> {
+        (0..self.time.len()).map(|i| self.value(unit, i)).collect()
+    }
+
+    /// Corrected raw value of `unit` in row `i`.
+    fn value(&self, unit: Unit, i: usize) -> f64 {
+        self.corrections.value(
+            unit,
+            f64::from(self.temperature[i]),
+            f64::from(self.humidity[i]),
+            f64::from(self.pressure[i]),
+        )
+    }
+
+    /// Tendency of the calibrated pressure up to the last row, the forecast
+    /// assumes the sensor is at the altitude in `settings`.
+    pub fn pressure_tendency(&self, settings: &SensorSettings) -> Option<Tendency> {
+        let scale = Unit::Pressure.scale();
+        Tendency::from_rows(&self.time, |i| {
+            let raw = f64::from(self.pressure[i]);
+            let pressure = settings.calibration.pressure.apply(raw) / scale;
+            let sea_level_pressure = physics::sea_level_pressure(
+                pressure,
+                settings.altitude,
+                settings.reference_temperature,
+            );
+            (pressure, sea_level_pressure)
+        })
+    }
+
+    /// Index range of the rows with `lower <= time < upper`, or all rows
+    /// if there are no bounds.
+    pub fn bounds_range(&self, bounds: Option<(u32, u32)>) -> Range<usize> {
+        match bounds {
+            None => 0..self.time.len(),
+            Some((lower, upper)) => {
+                let start = match self.time.iter().position(|t| *t >= lower) {
+                    Some(i) => i,
+                    None => return self.time.len()..self.time.len(),
+                };
+                let end = self.time[start..]
+                    .iter()
+                    .position(|t| *t >= upper)
+                    .map(|i| start + i)
+                    .unwrap_or_else(|| self.time.len());
+
+                start..end
+            }
+        }
+    }
+
+    /// Summary statistics of the column displayed for `unit`, restricted to
+    /// `bounds` like the graph. `None` if no rows are inside the bounds.
+    pub fn statistics(&self, unit: Unit, bounds: Option<(u32, u32)>) -> Option<Statistics> {
+        let range = self.bounds_range(bounds);
+        if range.is_empty() {
+            return None;
+        }
+        let time = &self.time[range.clone()];
+        let values: Vec<f64> = range.map(|i| self.value(unit, i)).collect();
+
+        let mut min = (time[0], values[0]);
+        let mut max = min;
+        for (&t, &value) in time.iter().zip(values.iter()).skip(1) {
+            if value < min.1 {
+                min = (t, value);
+            } else if value > max.1 {
+                max = (t, value);
+            }
+        }
+
+        let count = values.len();
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+
+        let mut sorted = values.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let median = if count % 2 == 0 {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2.
+        } else {
+            sorted[count / 2]
+        };
+
+        Some(Statistics {
+            min,
+            max,
+            mean,
+            median,
+            std_dev: variance.sqrt(),
+            delta: values[count - 1] - values[0],
+            count,
+        })
+    }
+
+    /// Summarize all rows into buckets of `bucket` seconds.
+    pub fn aggregate(&self, bucket: u32) -> AggregatedTimeseries {
+        self.aggregate_range(0..self.time.len(), bucket)
+    }
+
+    /// Summarize the rows in `rows` into buckets of `bucket` seconds.
+    pub fn aggregate_range(&self, rows: Range<usize>, bucket: u32) -> AggregatedTimeseries {
+        let bucket = bucket.max(1);
+        let mut ret = AggregatedTimeseries::default();
+        let mut acc: Option<(u32, Vec<Accumulator>)> = None;
+
+        for i in rows {
+            let start = self.time[i] - self.time[i] % bucket;
+            let values = Unit::ALL
+                .iter()
+                .map(|unit| self.value(*unit, i))
+                .collect::<Vec<_>>();
+            match acc {
+                Some((current, ref mut columns)) if current == start => {
+                    for (column, value) in columns.iter_mut().zip(values.iter()) {
+                        column.add(*value);
+                    }
+                }
+                _ => {
+                    if let Some((current, columns)) = acc.take() {
+                        ret.push_bucket(current.saturating_add(bucket / 2), &columns);
+                    }
+                    acc = Some((start, values.into_iter().map(Accumulator::new).collect()));
+                }
+            }
+        }
+
+        if let Some((current, columns)) = acc {
+            ret.push_bucket(current.saturating_add(bucket / 2), &columns);
+        }
+
+        ret
+    }
+
     pub fn push_row(&mut self, row: TimeseriesRow) -> Result<(), PushError> {
         match self.time.last() {
             Some(&time) if row.time <= time => Err(PushError {
@@ -127,6 +412,95 @@ impl Timeseries {
     }
 }
 
+/// Summary of a column in raw units, see [`Timeseries::statistics`].
+#[derive(Debug, Clone, Copy)]
+pub struct Statistics {
+    /// Timestamp and value of the first minimum
+    pub min: (u32, f64),
+    /// Timestamp and value of the first maximum
+    pub max: (u32, f64),
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64,
+    /// Last value minus first value
+    pub delta: f64,
+    pub count: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Accumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+    n: u32,
+}
+
+impl Accumulator {
+    fn new(value: f64) -> Self {
+        Self {
+            min: value,
+            max: value,
+            sum: value,
+            n: 1,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.n += 1;
+    }
+}
+
+/// Minimum, mean and maximum of a column per bucket.
+#[derive(Default, Debug)]
+pub struct AggregatedColumn {
+    pub min: Vec<f64>,
+    pub mean: Vec<f64>,
+    pub max: Vec<f64>,
+}
+
+impl AggregatedColumn {
+    fn push(&mut self, acc: &Accumulator) {
+        self.min.push(acc.min);
+        self.mean.push(acc.sum / f64::from(acc.n));
+        self.max.push(acc.max);
+    }
+}
+
+/// A [`Timeseries`] summarized into fixed size time buckets, `time` is the
+/// middle of each bucket.
+#[derive(Debug)]
+pub struct AggregatedTimeseries {
+    pub time: Vec<u32>,
+    /// indexed by [`Unit::as_usize`], derived quantities are summarized per
+    /// row instead of computed from the summaries
+    columns: Vec<AggregatedColumn>,
+}
+
+impl Default for AggregatedTimeseries {
+    fn default() -> Self {
+        Self {
+            time: Vec::new(),
+            columns: Unit::ALL.iter().map(|_| Default::default()).collect(),
+        }
+    }
+}
+
+impl AggregatedTimeseries {
+    fn push_bucket(&mut self, time: u32, columns: &[Accumulator]) {
+        self.time.push(time);
+        for (column, acc) in self.columns.iter_mut().zip(columns.iter()) {
+            column.push(acc);
+        }
+    }
+
+    pub fn column(&self, unit: Unit) -> &AggregatedColumn {
+        &self.columns[unit.as_usize()]
+    }
+}
+
 #[derive(Clone, glib::GSharedBoxed, Default)]
 #[gshared_boxed(type_name = "SharedTimeseries")]
 pub struct SharedTimeseries(pub Rc<Option<RefCell<Timeseries>>>);
@@ -159,3 +533,204 @@ pub struct TimeseriesRow {
     pub humidity: u32,
     pub pressure: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{find_label, raw_value, Corrections, Timeseries, TimeseriesBuilder};
+    use crate::{
+        local_store::{Calibration, Calibrations},
+        physics,
+        units::Unit,
+    };
+
+    /// Timeseries of `time` and `temperature` with constant humidity and pressure.
+    fn timeseries(time: &[u32], temperature: &[i16]) -> Timeseries {
+        TimeseriesBuilder::default()
+            .time(time.to_vec())
+            .temperature(temperature.to_vec())
+            .humidity(vec![5000; time.len()])
+            .pressure(vec![1_013_000; time.len()])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn aggregate() {
+        // buckets start at multiples of their size, a row on the edge starts the next one
+        let timeseries = timeseries(&[0, 30, 59, 60, 119, 240], &[100, 300, 200, 400, 400, -100]);
+        let aggregated = timeseries.aggregate(60);
+        assert_eq!(aggregated.time, vec![30, 90, 270]);
+        let temperature = aggregated.column(Unit::Temperature);
+        assert_eq!(temperature.min, vec![100., 400., -100.]);
+        assert_eq!(temperature.mean, vec![200., 400., -100.]);
+        assert_eq!(temperature.max, vec![300., 400., -100.]);
+        assert_eq!(aggregated.column(Unit::Humidity).mean, vec![5000.; 3]);
+    }
+
+    #[test]
+    fn aggregate_edges() {
+        let empty = timeseries(&[], &[]);
+        assert!(empty.aggregate(60).time.is_empty());
+        assert!(empty
+            .aggregate(60)
+            .column(Unit::Temperature)
+            .mean
+            .is_empty());
+
+        let single = timeseries(&[125], &[2150]).aggregate(60);
+        assert_eq!(single.time, vec![150]);
+        let temperature = single.column(Unit::Temperature);
+        assert_eq!(
+            (temperature.min[0], temperature.mean[0], temperature.max[0]),
+            (2150., 2150., 2150.)
+        );
+
+        // only the rows in the range get summarized
+        let timeseries = timeseries(&[0, 30, 60, 90], &[1, 2, 3, 4]);
+        let aggregated = timeseries.aggregate_range(1..3, 60);
+        assert_eq!(aggregated.time, vec![30, 90]);
+        assert_eq!(aggregated.column(Unit::Temperature).mean, vec![2., 3.]);
+        assert!(timeseries.aggregate_range(2..2, 60).time.is_empty());
+
+        // empty buckets are left out and a bucket of 0 s is one of 1 s
+        assert_eq!(timeseries.aggregate(20).time, vec![10, 30, 70, 90]);
+        assert_eq!(timeseries.aggregate(0).time, vec![0, 30, 60, 90]);
+    }
+
+    #[test]
+    fn corrections() {
+        let (temperature, humidity, pressure) = (2000., 5000., 950_000.);
+        let calibration = Calibrations {
+            temperature: Calibration {
+                offset: -150.,
+                two_point: None,
+            },
+            humidity: Calibration {
+                offset: 0.,
+                two_point: Some([(4000., 4200.), (6000., 6600.)]),
+            },
+            pressure: Calibration {
+                offset: 2000.,
+                two_point: None,
+            },
+        };
+        let calibrated = (1850., 5400., 952_000.);
+        let sea_level =
+            |pressure: f64| physics::sea_level_pressure(pressure / 1000., 500., Some(15.)) * 1000.;
+
+        for (corrections, expected) in [
+            (Corrections::default(), (temperature, humidity, pressure)),
+            (
+                Corrections {
+                    calibration: Some(calibration),
+                    ..Default::default()
+                },
+                calibrated,
+            ),
+            // the calibration is still known but not applied
+            (
+                Corrections {
+                    calibration: Some(calibration),
+                    raw_values: true,
+                    ..Default::default()
+                },
+                (temperature, humidity, pressure),
+            ),
+            (
+                Corrections {
+                    sea_level: Some((500., Some(15.))),
+                    ..Default::default()
+                },
+                (temperature, humidity, sea_level(pressure)),
+            ),
+            // the calibrated pressure gets reduced to sea level
+            (
+                Corrections {
+                    calibration: Some(calibration),
+                    sea_level: Some((500., Some(15.))),
+                    ..Default::default()
+                },
+                (calibrated.0, calibrated.1, sea_level(calibrated.2)),
+            ),
+        ] {
+            let value = |unit| corrections.value(unit, temperature, humidity, pressure);
+            assert_eq!(value(Unit::Temperature), expected.0, "{:?}", corrections);
+            assert_eq!(value(Unit::Humidity), expected.1, "{:?}", corrections);
+            assert_eq!(value(Unit::Pressure), expected.2, "{:?}", corrections);
+            // derived quantities use the corrected measurements
+            for unit in [Unit::DewPoint, Unit::HeatIndex, Unit::VapourPressureDeficit] {
+                assert_eq!(
+                    value(unit),
+                    raw_value(unit, expected.0, expected.1, expected.2),
+                    "{:?} {:?}",
+                    unit,
+                    corrections
+                );
+            }
+        }
+        assert!(sea_level(pressure) > pressure);
+    }
+
+    #[test]
+    fn calibrated_humidity_range() {
+        let corrections = |offset| Corrections {
+            calibration: Some(Calibrations {
+                humidity: Calibration {
+                    offset,
+                    two_point: None,
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        for (offset, humidity, expected) in [
+            (500., 9800., 10_000.),
+            (500., 9500., 10_000.),
+            (500., 9400., 9900.),
+            (-500., 300., 0.),
+            (-500., 600., 100.),
+        ] {
+            assert_eq!(
+                corrections(offset).value(Unit::Humidity, 2000., humidity, 1_013_000.),
+                expected,
+                "{} + {}",
+                humidity,
+                offset
+            );
+        }
+        // raw values aren't touched
+        let raw = Corrections {
+            raw_values: true,
+            ..corrections(500.)
+        };
+        assert_eq!(raw.value(Unit::Humidity, 2000., 9800., 1_013_000.), 9800.);
+    }
+
+    #[test]
+    fn label() {
+        let labels = [
+            Some("Living room".to_owned()),
+            None,
+            Some("Kitchen window".to_owned()),
+            Some("kitchen".to_owned()),
+            Some("Garden".to_owned()),
+        ];
+        for (wanted, expected) in [
+            ("Garden", Some(4)),
+            // an exact match wins over an earlier prefix
+            ("kitchen", Some(3)),
+            ("Kitchen", Some(2)),
+            ("KITCHEN W", Some(2)),
+            ("living", Some(0)),
+            ("gard", Some(4)),
+            // only from the start, no fuzzy or substring matches
+            ("room", None),
+            ("Kitchn", None),
+            ("Gardens", None),
+            ("", None),
+        ] {
+            assert_eq!(find_label(&labels, wanted), expected, "{:?}", wanted);
+        }
+        assert_eq!(find_label(&[], "Garden"), None);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::units::Unit;
+use time::{macros::format_description, Date, OffsetDateTime, PrimitiveDateTime, Time};
+use url::Url;
+use uuid::Uuid;
+
+pub const URI_SCHEME: &str = "blews";
+
+/// A sensor as given by the user.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SensorRef {
+    Id(Uuid),
+    Label(String),
+}
+
+impl SensorRef {
+    pub fn parse(s: &str) -> Self {
+        match Uuid::parse_str(s) {
+            Ok(id) => SensorRef::Id(id),
+            Err(_) => SensorRef::Label(s.to_owned()),
+        }
+    }
+}
+
+/// Request to open the detail view of a sensor, from the command line or a
+/// `blews://sensor/<uuid>?from=&to=&unit=` uri.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeepLink {
+    pub sensor: SensorRef,
+    pub unit: Option<Unit>,
+    /// static range to show, live data if `None`
+    pub range: Option<(OffsetDateTime, OffsetDateTime)>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid uri: {0}")]
+    Uri(#[from] url::ParseError),
+    #[error("Not a blews:// uri: {0}")]
+    NotDeepLink(String),
+    #[error("Invalid sensor id {0}")]
+    InvalidId(String),
+    #[error("Invalid time {0}, expected a unix timestamp, YYYY-MM-DD or YYYY-MM-DD HH:MM")]
+    InvalidTime(String),
+    #[error("Invalid unit {0}, expected one of temperature, humidity, pressure, dewpoint, absolutehumidity, heatindex or vapourpressuredeficit")]
+    InvalidUnit(String),
+    #[error("from is after to")]
+    InvalidRange,
+}
+
+impl DeepLink {
+    /// Build a link from the optional parts, a missing end of the range is
+    /// filled in with now or one day before `to`.
+    pub fn new(
+        sensor: SensorRef,
+        from: Option<&str>,
+        to: Option<&str>,
+        unit: Option<&str>,
+    ) -> Result<Self, Error> {
+        let unit = unit
+            .map(|unit| {
+                unit.parse()
+                    .map_err(|_| Error::InvalidUnit(unit.to_owned()))
+            })
+            .transpose()?;
+        let from = from.map(parse_time).transpose()?;
+        let to = to.map(parse_time).transpose()?;
+        let range = match (from, to) {
+            (None, None) => None,
+            (Some(from), None) => Some((from, crate::util::now_local())),
+            (None, Some(to)) => Some((to - time::Duration::DAY, to)),
+            (Some(from), Some(to)) => Some((from, to)),
+        };
+        if let Some((from, to)) = range {
+            if from > to {
+                return Err(Error::InvalidRange);
+            }
+        }
+
+        Ok(Self {
+            sensor,
+            unit,
+            range,
+        })
+    }
+
+    pub fn parse_uri(uri: &str) -> Result<Self, Error> {
+        let url = Url::parse(uri)?;
+        if url.scheme() != URI_SCHEME || url.host_str() != Some("sensor") {
+            return Err(Error::NotDeepLink(uri.to_owned()));
+        }
+        let id = url.path().trim_start_matches('/');
+        let id = Uuid::parse_str(id).map_err(|_| Error::InvalidId(id.to_owned()))?;
+
+        let mut from = None;
+        let mut to = None;
+        let mut unit = None;
+        for (key, value) in url.query_pairs() {
+            match &*key {
+                "from" if !value.is_empty() => from = Some(value),
+                "to" if !value.is_empty() => to = Some(value),
+                "unit" if !value.is_empty() => unit = Some(value),
+                _ => (),
+            }
+        }
+
+        Self::new(
+            SensorRef::Id(id),
+            from.as_deref(),
+            to.as_deref(),
+            unit.as_deref(),
+        )
+    }
+}
+
+fn parse_time(s: &str) -> Result<OffsetDateTime, Error> {
+    let invalid = || Error::InvalidTime(s.to_owned());
+    if let Ok(timestamp) = s.parse::<i64>() {
+        return OffsetDateTime::from_unix_timestamp(timestamp).map_err(|_| invalid());
+    }
+
+    let datetime = PrimitiveDateTime::parse(
+        s,
+        &format_description!("[year]-[month]-[day] [hour]:[minute]"),
+    )
+    .or_else(|_| {
+        Date::parse(s, &format_description!("[year]-[month]-[day]"))
+            .map(|date| date.with_time(Time::MIDNIGHT))
+    })
+    .map_err(|_| invalid())?;
+    Ok(datetime.assume_offset(crate::util::now_local().offset()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "6ba7b810-9dad-11d1-80b4-00c04fd430c8";
+
+    fn timestamp(timestamp: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(timestamp).unwrap()
+    }
+
+    #[test]
+    fn sensor_ref() {
+        let id = Uuid::parse_str(ID).unwrap();
+        for (s, expected) in [
+            (ID, SensorRef::Id(id)),
+            ("6BA7B810-9DAD-11D1-80B4-00C04FD430C8", SensorRef::Id(id)),
+            ("6ba7b8109dad11d180b400c04fd430c8", SensorRef::Id(id)),
+            ("Kitchen", SensorRef::Label("Kitchen".to_owned())),
+            // almost an id
+            (
+                "6ba7b810-9dad-11d1-80b4-00c04fd430c",
+                SensorRef::Label("6ba7b810-9dad-11d1-80b4-00c04fd430c".to_owned()),
+            ),
+            ("", SensorRef::Label(String::new())),
+        ] {
+            assert_eq!(SensorRef::parse(s), expected, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn new() {
+        let sensor = SensorRef::Label("Kitchen".to_owned());
+        let new = |from, to, unit| DeepLink::new(sensor.clone(), from, to, unit);
+
+        for (from, to, unit, range, expected_unit) in [
+            (None, None, None, None, None),
+            (None, None, Some("humidity"), None, Some(Unit::Humidity)),
+            (None, None, Some("dewpoint"), None, Some(Unit::DewPoint)),
+            (
+                Some("1600000000"),
+                Some("1600003600"),
+                None,
+                Some((timestamp(1600000000), timestamp(1600003600))),
+                None,
+            ),
+            (
+                None,
+                Some("1600086400"),
+                Some("pressure"),
+                Some((timestamp(1600000000), timestamp(1600086400))),
+                Some(Unit::Pressure),
+            ),
+            // an empty range is fine
+            (
+                Some("1600000000"),
+                Some("1600000000"),
+                None,
+                Some((timestamp(1600000000), timestamp(1600000000))),
+                None,
+            ),
+        ] {
+            let link = new(from, to, unit).unwrap();
+            assert_eq!(link.sensor, sensor);
+            assert_eq!(link.range, range, "{:?}..{:?}", from, to);
+            assert_eq!(link.unit, expected_unit, "{:?}", unit);
+        }
+
+        // up to now when only the start is given
+        let link = new(Some("1600000000"), None, None).unwrap();
+        let (from, to) = link.range.unwrap();
+        assert_eq!(from, timestamp(1600000000));
+        assert!(to > timestamp(1600000000));
+
+        assert!(matches!(
+            new(Some("1600003600"), Some("1600000000"), None),
+            Err(Error::InvalidRange)
+        ));
+        for unit in ["kelvin", "Temperature", ""] {
+            assert!(
+                matches!(new(None, None, Some(unit)), Err(Error::InvalidUnit(u)) if u == unit),
+                "{:?}",
+                unit
+            );
+        }
+        assert!(matches!(
+            new(Some("yesterday"), None, None),
+            Err(Error::InvalidTime(t)) if t == "yesterday"
+        ));
+        assert!(matches!(
+            new(None, Some("2021-02-30"), None),
+            Err(Error::InvalidTime(t)) if t == "2021-02-30"
+        ));
+    }
+
+    #[test]
+    fn parse_uri() {
+        let id = Uuid::parse_str(ID).unwrap();
+        for (uri, range, unit) in [
+            (format!("blews://sensor/{}", ID), None, None),
+            (
+                format!(
+                    "blews://sensor/{}?from=1600000000&to=1600003600&unit=temperature",
+                    ID
+                ),
+                Some((timestamp(1600000000), timestamp(1600003600))),
+                Some(Unit::Temperature),
+            ),
+            // empty and unknown parameters are ignored
+            (
+                format!("blews://sensor/{}?from=&unit=&zoom=2", ID),
+                None,
+                None,
+            ),
+        ] {
+            let link = DeepLink::parse_uri(&uri).unwrap();
+            assert_eq!(link.sensor, SensorRef::Id(id), "{}", uri);
+            assert_eq!(link.range, range, "{}", uri);
+            assert_eq!(link.unit, unit, "{}", uri);
+        }
+
+        for uri in [
+            format!("https://sensor/{}", ID),
+            format!("blews://graph/{}", ID),
+            format!("blews:///{}", ID),
+            format!("blews:sensor/{}", ID),
+        ] {
+            assert!(
+                matches!(DeepLink::parse_uri(&uri), Err(Error::NotDeepLink(_))),
+                "{}",
+                uri
+            );
+        }
+        for (uri, id) in [
+            ("blews://sensor/Kitchen".to_owned(), "Kitchen"),
+            ("blews://sensor/".to_owned(), ""),
+            (
+                format!("blews://sensor/{}/1", ID),
+                "6ba7b810-9dad-11d1-80b4-00c04fd430c8/1",
+            ),
+        ] {
+            assert!(
+                matches!(DeepLink::parse_uri(&uri), Err(Error::InvalidId(i)) if i == id),
+                "{}",
+                uri
+            );
+        }
+        assert!(matches!(
+            DeepLink::parse_uri("not a uri"),
+            Err(Error::Uri(_))
+        ));
+        assert!(matches!(
+            DeepLink::parse_uri(&format!("blews://sensor/{}?unit=celsius", ID)),
+            Err(Error::InvalidUnit(_))
+        ));
+        assert!(matches!(
+            DeepLink::parse_uri(&format!("blews://sensor/{}?from=2&to=1", ID)),
+            Err(Error::InvalidRange)
+        ));
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time("1600000000").unwrap(), timestamp(1600000000));
+        assert_eq!(parse_time("0").unwrap(), timestamp(0));
+
+        // dates are in the local time zone, so only compare them to each other
+        let midnight = parse_time("2021-06-01").unwrap();
+        assert_eq!(parse_time("2021-06-01 00:00").unwrap(), midnight);
+        assert_eq!(
+            parse_time("2021-06-01 12:30").unwrap() - midnight,
+            time::Duration::minutes(12 * 60 + 30)
+        );
+        assert_eq!(
+            parse_time("2021-06-02").unwrap() - midnight,
+            time::Duration::DAY
+        );
+
+        for s in [
+            "",
+            "now",
+            "1600000000.5",
+            "99999999999999999",
+            "2021-13-01",
+            "2021-06-31",
+            "2021-06-01 24:00",
+            "2021-06-01T12:30",
+            "01.06.2021",
+        ] {
+            assert!(
+                matches!(parse_time(s), Err(Error::InvalidTime(t)) if t == s),
+                "{:?}",
+                s
+            );
+        }
+    }
+}
@@ -1,14 +1,19 @@
 use crate::{
-    data::Data,
+    alerts::{self, AlertEngine, AlertHistory, HistoryEntry},
+    data::{Corrections, Data, SensorValues},
+    deep_link::DeepLink,
+    local_store::{Calibrations, LocalStore, SensorMetadata, SensorSettings, Thresholds},
     sensor_obj::SensorObj,
     services::{
         self,
         central::{TimeseriesRequest, TimeseriesResponse},
+        dbus::DbusServer,
     },
-    widgets::graph::Unit,
+    units::{Unit, UnitSystem},
 };
 use ble_ws_api::data::Timestamp;
-use gio::prelude::*;
+use gtk::prelude::*;
+use std::path::PathBuf;
 use url::Url;
 use uuid::Uuid;
 
@@ -16,15 +21,61 @@ pub enum Event {
     OpenSearch,
     OpenOverview,
     OpenDetail(Uuid),
+    /// Bring up the window with the detail view of a sensor
+    ShowSensor(Uuid),
+    OpenLink(DeepLink),
+    OpenComparison,
+    OpenAlerts,
+    OpenAlert {
+        id: Uuid,
+        time: u32,
+    },
     DetailRangeChanged {
         id: Uuid,
         from: time::OffsetDateTime,
         to: time::OffsetDateTime,
     },
+    DetailOverlayChanged {
+        id: Uuid,
+        from: time::OffsetDateTime,
+        to: time::OffsetDateTime,
+        shift: u32,
+    },
     ChangeLabel {
         id: Uuid,
         label: Label,
     },
+    ChangeThresholds {
+        id: Uuid,
+        thresholds: Thresholds,
+    },
+    ChangeLocation {
+        id: Uuid,
+        /// in m
+        altitude: f64,
+        /// in °C
+        reference_temperature: Option<f64>,
+    },
+    ChangeCalibration {
+        id: Uuid,
+        calibration: Calibrations,
+    },
+    ChangeMetadata {
+        id: Uuid,
+        metadata: SensorMetadata,
+    },
+    ChangeHidden {
+        id: Uuid,
+        hidden: bool,
+    },
+    ChangeFavorite {
+        id: Uuid,
+        favorite: bool,
+    },
+    ImportMetadata(PathBuf),
+    ExportMetadata(PathBuf),
+    AlertRulesChanged,
+    UnitsChanged,
     OverviewUpdate(Vec<(Uuid, ble_ws_api::proto::SensorOverview)>),
     FetchedTimeseries {
         timeseries: TimeseriesResponse,
@@ -33,12 +84,41 @@ pub enum Event {
     SensorSelected(Uuid),
 }
 
+/// Hours of data shown around an alert opened from the history.
+const ALERT_CONTEXT_HOURS: i64 = 6;
+
 pub fn attach(tx: glib::Sender<Event>, rx: glib::Receiver<Event>, window: crate::widgets::Window) {
-    let mut state = State::default();
+    let mut state = State {
+        data: Default::default(),
+        display: Default::default(),
+        local: LocalStore::load(),
+        alerts: Default::default(),
+        history: AlertHistory::load(),
+        pending_link: None,
+    };
+    window.set_alert_history(state.history.entries());
+    if let Some(app) = window.application() {
+        // changes are saved with a delay, don't lose the last ones on quit
+        let local = state.local.clone();
+        app.connect_shutdown(move |_| local.flush());
+    }
     let svcs = services::ServiceManager::new(tx.clone()).unwrap();
 
     let central = svcs.create_service::<services::central::Central>().unwrap();
+    let dbus = DbusServer::session(services::dbus::Handlers {
+        fetch_range: {
+            let central = central.clone();
+            Box::new(move |id, range| central.fetch_range_blocking(id, range))
+        },
+        show_sensor: {
+            let tx = tx.clone();
+            Box::new(move |id| tx.send(Event::ShowSensor(id)).unwrap())
+        },
+    })
+    .map_err(|e| tracing::error!("Could not export sensors on the session bus: {}", e))
+    .ok();
     let settings = crate::config::settings();
+    state.alerts.set_rules(alerts::load_rules(&settings));
 
     // FIXME: get token from secret service instead
     let token_file = std::env::var_os("TOKEN_FILE").expect("Missing TOKEN_FILE env var");
@@ -61,6 +141,27 @@ pub fn attach(tx: glib::Sender<Event>, rx: glib::Receiver<Event>, window: crate:
         }
     });
 
+    settings.connect_changed(Some("alert-rules"), {
+        let tx = tx.clone();
+        move |_, _| {
+            tx.send(Event::AlertRulesChanged).unwrap();
+        }
+    });
+
+    for key in &[
+        "temperature-unit",
+        "pressure-unit",
+        "sea-level-pressure",
+        "raw-values",
+    ] {
+        settings.connect_changed(Some(key), {
+            let tx = tx.clone();
+            move |_, _| {
+                tx.send(Event::UnitsChanged).unwrap();
+            }
+        });
+    }
+
     rx.attach(None, {
         move |evt| {
             match evt {
@@ -72,6 +173,21 @@ pub fn attach(tx: glib::Sender<Event>, rx: glib::Receiver<Event>, window: crate:
                         range: from..=to,
                     });
                 }
+                Event::DetailOverlayChanged {
+                    id,
+                    from,
+                    to,
+                    shift,
+                } => {
+                    let from =
+                        Timestamp::from((from.unix_timestamp() as u32).saturating_sub(shift));
+                    let to = Timestamp::from((to.unix_timestamp() as u32).saturating_sub(shift));
+                    central.fetch_timeseries(TimeseriesRequest::ShiftedRange {
+                        id,
+                        range: from..=to,
+                        shift,
+                    });
+                }
                 Event::OpenSearch => {
                     if let View::Overview {
                         ref mut search_active,
@@ -89,7 +205,7 @@ pub fn attach(tx: glib::Sender<Event>, rx: glib::Receiver<Event>, window: crate:
 
                 Event::SensorSelected(id) => {
                     if let Some(sensor) = state.data.sensors.get(&id) {
-                        if sensor.timeseries().is_none() {
+                        if !sensor.has_live_timeseries() {
                             central.fetch_timeseries(TimeseriesRequest::Live(id));
                         }
 
@@ -118,40 +234,159 @@ pub fn attach(tx: glib::Sender<Event>, rx: glib::Receiver<Event>, window: crate:
                             TimeseriesResponse::Live(timeseries) => {
                                 obj.set_timeseries(timeseries);
                             }
-                            TimeseriesResponse::Range(timeseries) => match &state.display {
+                            TimeseriesResponse::Tendency(timeseries) => {
+                                obj.set_tendency_timeseries(timeseries);
+                            }
+                            TimeseriesResponse::Range(mut timeseries) => match &state.display {
                                 View::Detail { sensor, .. } if sensor.id() == id => {
+                                    timeseries.set_corrections(obj.corrections());
                                     window.set_static_timeseries(timeseries);
                                 }
                                 _ => (),
                             },
+                            TimeseriesResponse::ShiftedRange {
+                                mut timeseries,
+                                shift,
+                            } => match &state.display {
+                                View::Detail { sensor, .. } if sensor.id() == id => {
+                                    timeseries.set_corrections(obj.corrections());
+                                    window.set_overlay_timeseries(timeseries, shift);
+                                }
+                                _ => (),
+                            },
                         }
                     }
                 }
 
                 Event::OverviewUpdate(update) => {
+                    let now = Timestamp::now().as_u32();
                     for (addr, data) in update {
-                        match state.data.sensors.get(&addr) {
+                        let connected = data.values.is_some();
+                        let sensor = match state.data.sensors.get(&addr) {
                             Some(sensor) => {
                                 sensor.update_values(data);
+                                sensor.clone()
                             }
                             None => {
                                 let sensor = SensorObj::new(addr);
+                                let sensor_settings = state.local.get(addr);
+                                sensor.set_corrections(corrections(&settings, &sensor_settings));
+                                sensor.set_settings(sensor_settings);
                                 sensor.update_values(data);
                                 window.add_sensor(&sensor);
-                                state.data.sensors.insert(addr, sensor);
+                                state.data.sensors.insert(addr, sensor.clone());
+                                // the whole live history is only fetched once it is shown
+                                if !sensor.is_hidden() {
+                                    central.fetch_timeseries(TimeseriesRequest::Tendency(addr));
+                                }
+                                sensor
+                            }
+                        };
+
+                        // hidden sensors are ignored, not only left out of the overview
+                        if sensor.is_hidden() {
+                            continue;
+                        }
+
+                        // alerts check calibrated values even while raw ones are shown
+                        let values = connected.then(|| SensorValues {
+                            corrections: Corrections {
+                                raw_values: false,
+                                ..sensor.corrections()
+                            },
+                            ..sensor.values()
+                        });
+                        for alert in state.alerts.evaluate(addr, values, now) {
+                            let label = sensor
+                                .data()
+                                .label
+                                .borrow()
+                                .clone()
+                                .unwrap_or_else(|| addr.to_string());
+                            if let Some(app) = window.application() {
+                                alerts::send_notification(app.upcast_ref(), &label, &alert);
                             }
+                            let entry = HistoryEntry {
+                                sensor_label: label,
+                                event: alert,
+                            };
+                            state.history.push(entry.clone());
+                            window.push_alert_history(entry);
                         }
                     }
+
+                    export_sensors(dbus.as_ref(), &state.data);
+
+                    // links from the command line can arrive before the sensors
+                    if let Some(link) = state.pending_link.take() {
+                        tx.send(Event::OpenLink(link)).unwrap();
+                    }
                 }
 
                 Event::OpenDetail(addr) => {
                     if let Some(sensor) = state.data.sensors.get(&addr) {
-                        if sensor.timeseries().is_none() {
+                        if !sensor.has_live_timeseries() {
                             central.fetch_timeseries(TimeseriesRequest::Live(addr));
                         }
                         state.display = View::Detail {
                             sensor: sensor.clone(),
                             unit: Unit::Temperature,
+                            range: None,
+                        };
+
+                        window.switch_view(&state.display);
+                    }
+                }
+
+                Event::ShowSensor(id) => {
+                    if let Some(app) = window.application() {
+                        app.activate();
+                    }
+                    tx.send(Event::OpenDetail(id)).unwrap();
+                }
+
+                Event::OpenLink(link) => match state.data.find_sensor(&link.sensor) {
+                    Some(sensor) => {
+                        if !sensor.has_live_timeseries() {
+                            central.fetch_timeseries(TimeseriesRequest::Live(sensor.id()));
+                        }
+                        state.display = View::Detail {
+                            sensor: sensor.clone(),
+                            unit: link.unit.unwrap_or_default(),
+                            range: link.range,
+                        };
+                        window.switch_view(&state.display);
+                    }
+                    None if state.data.sensors.is_empty() => state.pending_link = Some(link),
+                    None => tracing::error!("No sensor matching {:?}", link.sensor),
+                },
+
+                Event::OpenComparison => {
+                    for sensor in visible_sensors(&state.data) {
+                        if !sensor.has_live_timeseries() {
+                            central.fetch_timeseries(TimeseriesRequest::Live(sensor.id()));
+                        }
+                    }
+                    state.display = View::Comparison {
+                        sensors: visible_sensors(&state.data).cloned().collect(),
+                    };
+
+                    window.switch_view(&state.display);
+                }
+
+                Event::OpenAlerts => {
+                    state.display = View::Alerts;
+                    window.switch_view(&state.display);
+                }
+
+                Event::OpenAlert { id, time } => {
+                    if let Some(sensor) = state.data.sensors.get(&id) {
+                        let center = crate::util::local_datetime(time);
+                        let half = time::Duration::hours(ALERT_CONTEXT_HOURS / 2);
+                        state.display = View::Detail {
+                            sensor: sensor.clone(),
+                            unit: Unit::Temperature,
+                            range: Some((center - half, center + half)),
                         };
 
                         window.switch_view(&state.display);
@@ -161,16 +396,169 @@ pub fn attach(tx: glib::Sender<Event>, rx: glib::Receiver<Event>, window: crate:
                 Event::ChangeLabel { label, id } => {
                     central.set_label(id, label);
                 }
+
+                Event::AlertRulesChanged => {
+                    state.alerts.set_rules(alerts::load_rules(&settings));
+                }
+
+                Event::UnitsChanged => {
+                    UnitSystem::from_settings(&settings).set_current();
+                    for sensor in state.data.sensors.values() {
+                        sensor.set_corrections(corrections(&settings, &sensor.settings()));
+                        // rerun the transforms of the sensor row bindings
+                        sensor.notify("temperature");
+                        sensor.notify("humidity");
+                        sensor.notify("pressure");
+                        sensor.notify("tendency");
+                    }
+                    window.refresh_units();
+                    export_sensors(dbus.as_ref(), &state.data);
+                }
+
+                Event::ChangeThresholds { id, thresholds } => {
+                    let settings = state
+                        .local
+                        .update(id, |settings| settings.thresholds = thresholds);
+                    if let Some(sensor) = state.data.sensors.get(&id) {
+                        sensor.set_settings(settings);
+                    }
+                }
+
+                Event::ChangeCalibration { id, calibration } => {
+                    let sensor_settings = state.local.update(id, |sensor_settings| {
+                        sensor_settings.calibration = calibration
+                    });
+                    if let Some(sensor) = state.data.sensors.get(&id) {
+                        sensor.set_corrections(corrections(&settings, &sensor_settings));
+                        sensor.set_settings(sensor_settings);
+                    }
+                    export_sensors(dbus.as_ref(), &state.data);
+                }
+
+                Event::ChangeLocation {
+                    id,
+                    altitude,
+                    reference_temperature,
+                } => {
+                    let sensor_settings = state.local.update(id, |sensor_settings| {
+                        sensor_settings.altitude = altitude;
+                        sensor_settings.reference_temperature = reference_temperature;
+                    });
+                    if let Some(sensor) = state.data.sensors.get(&id) {
+                        sensor.set_corrections(corrections(&settings, &sensor_settings));
+                        sensor.set_settings(sensor_settings);
+                    }
+                    export_sensors(dbus.as_ref(), &state.data);
+                }
+
+                Event::ChangeMetadata { id, metadata } => {
+                    let settings = state
+                        .local
+                        .update(id, |settings| settings.metadata = metadata);
+                    if let Some(sensor) = state.data.sensors.get(&id) {
+                        sensor.set_settings(settings);
+                    }
+                    // the room and tags can be searched for from the shell
+                    export_sensors(dbus.as_ref(), &state.data);
+                }
+
+                Event::ChangeHidden { id, hidden } => {
+                    let settings = state.local.update(id, |settings| settings.hidden = hidden);
+                    if hidden {
+                        // hidden sensors aren't evaluated, their alerts would stay firing
+                        let firing = state.alerts.clear(id);
+                        if let Some(app) = window.application() {
+                            for rule in &firing {
+                                alerts::withdraw_notification(app.upcast_ref(), rule);
+                            }
+                        }
+                    }
+                    if let Some(sensor) = state.data.sensors.get(&id) {
+                        sensor.set_settings(settings);
+                        if !hidden && sensor.timeseries().is_none() {
+                            central.fetch_timeseries(TimeseriesRequest::Tendency(id));
+                        }
+                    }
+                    export_sensors(dbus.as_ref(), &state.data);
+                }
+
+                Event::ChangeFavorite { id, favorite } => {
+                    let settings = state
+                        .local
+                        .update(id, |settings| settings.favorite = favorite);
+                    if let Some(sensor) = state.data.sensors.get(&id) {
+                        sensor.set_settings(settings);
+                    }
+                }
+
+                Event::ImportMetadata(path) => match state.local.import_metadata(&path) {
+                    Ok(changed) => {
+                        for (id, settings) in changed {
+                            if let Some(sensor) = state.data.sensors.get(&id) {
+                                sensor.set_settings(settings);
+                            }
+                        }
+                        export_sensors(dbus.as_ref(), &state.data);
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed importing metadata from {}: {}", path.display(), e);
+                        crate::widgets::show_error(
+                            &window,
+                            "Could not import the sensor metadata",
+                            &e,
+                        );
+                    }
+                },
+
+                Event::ExportMetadata(path) => {
+                    if let Err(e) = state.local.export_metadata(&path) {
+                        tracing::error!("Failed exporting metadata to {}: {}", path.display(), e);
+                        crate::widgets::show_error(
+                            &window,
+                            "Could not export the sensor metadata",
+                            &e,
+                        );
+                    }
+                }
             };
             glib::Continue(true)
         }
     });
 }
 
-#[derive(Default)]
+/// Corrections for a sensor with `sensor_settings` given the global settings.
+fn corrections(settings: &gio::Settings, sensor_settings: &SensorSettings) -> Corrections {
+    Corrections::new(
+        sensor_settings,
+        settings.boolean("sea-level-pressure"),
+        settings.boolean("raw-values"),
+    )
+}
+
+/// Sensors that aren't hidden, the only ones shared with other applications.
+fn visible_sensors(data: &Data) -> impl Iterator<Item = &SensorObj> {
+    data.sensors.values().filter(|sensor| !sensor.is_hidden())
+}
+
+/// Share the visible sensors on the session bus, after their values or
+/// corrections changed.
+fn export_sensors(dbus: Option<&DbusServer>, data: &Data) {
+    if let Some(dbus) = dbus {
+        dbus.set_sensors(
+            visible_sensors(data)
+                .map(|sensor| (sensor.into(), sensor.corrections()))
+                .collect(),
+        );
+    }
+}
+
 struct State {
     data: Data,
     display: View,
+    local: LocalStore,
+    alerts: AlertEngine,
+    history: AlertHistory,
+    pending_link: Option<DeepLink>,
 }
 
 pub enum View {
@@ -181,7 +569,13 @@ pub enum View {
     Detail {
         sensor: SensorObj,
         unit: Unit,
+        /// static range to show instead of live data
+        range: Option<(time::OffsetDateTime, time::OffsetDateTime)>,
+    },
+    Comparison {
+        sensors: Vec<SensorObj>,
     },
+    Alerts,
 }
 
 impl Default for View {
@@ -0,0 +1,203 @@
+//! Pressure tendency over the last hours and a Zambretti forecast from it.
+
+/// Span the tendency is measured over in seconds.
+pub const TENDENCY_SECONDS: u32 = 3 * 60 * 60;
+
+/// How far the oldest row may be off from the start of the span.
+const TENDENCY_TOLERANCE: u32 = 15 * 60;
+
+/// History in seconds needed to compute the tendency.
+pub const TENDENCY_HISTORY_SECONDS: u32 = TENDENCY_SECONDS + TENDENCY_TOLERANCE;
+
+/// Change in hPa over the span below which the pressure counts as steady.
+const STEADY_LIMIT: f64 = 1.6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+impl Trend {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Trend::Rising => "Rising",
+            Trend::Falling => "Falling",
+            Trend::Steady => "Steady",
+        }
+    }
+
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            Trend::Rising => "pan-up-symbolic",
+            Trend::Falling => "pan-down-symbolic",
+            Trend::Steady => "pan-end-symbolic",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, glib::GBoxed)]
+#[gboxed(type_name = "BleWsTendency", nullable)]
+pub struct Tendency {
+    pub trend: Trend,
+    /// change of the station pressure over the span in hPa
+    pub change: f64,
+    /// latest pressure reduced to sea level in hPa
+    pub sea_level_pressure: f64,
+}
+
+impl Tendency {
+    pub fn new(change: f64, sea_level_pressure: f64) -> Self {
+        let trend = if change >= STEADY_LIMIT {
+            Trend::Rising
+        } else if change <= -STEADY_LIMIT {
+            Trend::Falling
+        } else {
+            Trend::Steady
+        };
+        Self {
+            trend,
+            change,
+            sea_level_pressure,
+        }
+    }
+
+    /// Tendency of the rows with timestamps `time` up to the last one,
+    /// `pressure` gives the station and sea level pressure of a row in hPa.
+    /// `None` if the rows don't reach back far enough.
+    pub fn from_rows(time: &[u32], pressure: impl Fn(usize) -> (f64, f64)) -> Option<Self> {
+        let last = time.len().checked_sub(1)?;
+        let start = time[last].checked_sub(TENDENCY_SECONDS)?;
+        // the row closest to the start of the span
+        let (first, _) = time
+            .iter()
+            .enumerate()
+            .filter(|(_, &t)| t + TENDENCY_TOLERANCE >= start && t <= start + TENDENCY_TOLERANCE)
+            .min_by_key(|(_, &t)| (i64::from(t) - i64::from(start)).abs())?;
+        let (now, sea_level_pressure) = pressure(last);
+        let (then, _) = pressure(first);
+        Some(Self::new(now - then, sea_level_pressure))
+    }
+
+    /// Forecast for the next hours after the simplified Zambretti
+    /// forecaster, ignoring wind and season.
+    pub fn forecast(&self) -> &'static str {
+        let pressure = self.sea_level_pressure;
+        // forecast number, the first number of the trend and its letters
+        let (z, first, letters): (f64, f64, &[u8]) = match self.trend {
+            Trend::Falling => (127. - 0.12 * pressure, 1., b"ABDHORUXZ"),
+            Trend::Steady => (144. - 0.13 * pressure, 10., b"ABEKNPSWXZ"),
+            Trend::Rising => (185. - 0.16 * pressure, 20., b"ABCFGIJLMQTYZ"),
+        };
+        let i = (z.round() - first).clamp(0., (letters.len() - 1) as f64) as usize;
+        FORECASTS[usize::from(letters[i] - b'A')]
+    }
+}
+
+/// Zambretti forecasts A to Z.
+const FORECASTS: [&str; 26] = [
+    "Settled fine",
+    "Fine weather",
+    "Becoming fine",
+    "Fine, becoming less settled",
+    "Fine, possible showers",
+    "Fairly fine, improving",
+    "Fairly fine, possible showers early",
+    "Fairly fine, showery later",
+    "Showery early, improving",
+    "Changeable, mending",
+    "Fairly fine, showers likely",
+    "Rather unsettled, clearing later",
+    "Unsettled, probably improving",
+    "Showery, bright intervals",
+    "Showery, becoming less settled",
+    "Changeable, some rain",
+    "Unsettled, short fine intervals",
+    "Unsettled, rain later",
+    "Unsettled, some rain",
+    "Mostly very unsettled",
+    "Occasional rain, worsening",
+    "Rain at times, very unsettled",
+    "Rain at frequent intervals",
+    "Rain, very unsettled",
+    "Stormy, may improve",
+    "Stormy, much rain",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{Tendency, Trend, TENDENCY_SECONDS};
+
+    #[test]
+    fn trend() {
+        // (change in hPa, trend)
+        let cases = [
+            (0., Trend::Steady),
+            (1.5, Trend::Steady),
+            (-1.5, Trend::Steady),
+            (1.6, Trend::Rising),
+            (4., Trend::Rising),
+            (-1.6, Trend::Falling),
+            (-4., Trend::Falling),
+        ];
+        for &(change, expected) in &cases {
+            assert_eq!(
+                Tendency::new(change, 1013.).trend,
+                expected,
+                "{} hPa",
+                change
+            );
+        }
+    }
+
+    #[test]
+    fn from_rows() {
+        let hour = 60 * 60;
+        // one row every 10 minutes over four hours, rising 1 hPa per hour
+        let time = (0..=24).map(|i| 1_000_000 + i * 600).collect::<Vec<u32>>();
+        let pressure = |i: usize| {
+            let p = 1000. + f64::from(time[i] - time[0]) / f64::from(hour);
+            (p, p + 12.)
+        };
+        let tendency = Tendency::from_rows(&time, pressure).unwrap();
+        assert_eq!(tendency.trend, Trend::Rising);
+        assert!((tendency.change - 3.).abs() < 1e-9, "{}", tendency.change);
+        assert!((tendency.sea_level_pressure - 1016.).abs() < 1e-9);
+
+        // rows only reach back two hours
+        assert_eq!(Tendency::from_rows(&time[12..], pressure), None);
+        // a gap around the start of the span is tolerated up to 15 minutes
+        let last = time[time.len() - 1];
+        let gap = [last - TENDENCY_SECONDS + 10 * 60, last];
+        assert!(Tendency::from_rows(&gap, |_| (1000., 1000.)).is_some());
+        let gap = [last - TENDENCY_SECONDS + 20 * 60, last];
+        assert_eq!(Tendency::from_rows(&gap, |_| (1000., 1000.)), None);
+        assert_eq!(Tendency::from_rows(&[], |_| (1000., 1000.)), None);
+    }
+
+    #[test]
+    fn forecast() {
+        // (change in hPa, sea level pressure in hPa, forecast)
+        #[rustfmt::skip]
+        let cases = [
+            (0., 1020., "Fine weather"),
+            (0., 1000., "Showery, bright intervals"),
+            (-3., 1000., "Occasional rain, worsening"),
+            (-3., 1040., "Fine weather"),
+            (-3., 950., "Stormy, much rain"),
+            (3., 1030., "Settled fine"),
+            (3., 980., "Unsettled, probably improving"),
+            (3., 930., "Stormy, much rain"),
+        ];
+        for &(change, pressure, expected) in &cases {
+            assert_eq!(
+                Tendency::new(change, pressure).forecast(),
+                expected,
+                "{} hPa at {} hPa",
+                change,
+                pressure
+            );
+        }
+    }
+}
@@ -0,0 +1,665 @@
+use crate::units::Unit;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// Per sensor settings that only exist on this machine.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize, glib::GBoxed)]
+#[gboxed(type_name = "BleWsSensorSettings")]
+#[serde(default)]
+pub struct SensorSettings {
+    pub thresholds: Thresholds,
+    /// height above sea level in m
+    pub altitude: f64,
+    /// temperature in °C to reduce the pressure to sea level with instead
+    /// of the standard atmosphere
+    pub reference_temperature: Option<f64>,
+    pub calibration: Calibrations,
+    pub metadata: SensorMetadata,
+    /// left out of the overview, alerts and exports, e.g. a neighbour's sensor
+    pub hidden: bool,
+    /// pinned to the top of the overview
+    pub favorite: bool,
+}
+
+/// Description of a sensor, can be shared with other machines by
+/// [`LocalStore::export_metadata`].
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensorMetadata {
+    /// room or other location the sensor is placed in
+    pub room: String,
+    pub notes: String,
+    pub tags: Vec<String>,
+    /// name of a symbolic icon shown next to the label
+    pub icon: Option<String>,
+    pub color: Option<SensorColor>,
+}
+
+impl SensorMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Room and tags in a single line, empty if neither is set.
+    pub fn summary(&self) -> String {
+        let tags = self.tags.join(", ");
+        match (self.room.is_empty(), tags.is_empty()) {
+            (false, false) => format!("{} · {}", self.room, tags),
+            (false, true) => self.room.clone(),
+            (true, _) => tags,
+        }
+    }
+}
+
+/// Colors from the GNOME palette a sensor can be marked with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorColor {
+    Blue,
+    Green,
+    Yellow,
+    Orange,
+    Red,
+    Purple,
+    Brown,
+}
+
+impl SensorColor {
+    pub const ALL: [SensorColor; 7] = [
+        SensorColor::Blue,
+        SensorColor::Green,
+        SensorColor::Yellow,
+        SensorColor::Orange,
+        SensorColor::Red,
+        SensorColor::Purple,
+        SensorColor::Brown,
+    ];
+
+    /// Style class setting the foreground color, defined in style.css.
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            SensorColor::Blue => "sensor-color-blue",
+            SensorColor::Green => "sensor-color-green",
+            SensorColor::Yellow => "sensor-color-yellow",
+            SensorColor::Orange => "sensor-color-orange",
+            SensorColor::Red => "sensor-color-red",
+            SensorColor::Purple => "sensor-color-purple",
+            SensorColor::Brown => "sensor-color-brown",
+        }
+    }
+}
+
+/// Allowed range of a single unit in raw sensor units.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Threshold {
+    pub lower: Option<f64>,
+    pub upper: Option<f64>,
+}
+
+impl Threshold {
+    pub fn is_set(&self) -> bool {
+        self.lower.is_some() || self.upper.is_some()
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        self.lower.map_or(true, |lower| value >= lower)
+            && self.upper.map_or(true, |upper| value <= upper)
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    pub temperature: Threshold,
+    pub humidity: Threshold,
+    pub pressure: Threshold,
+}
+
+impl Thresholds {
+    /// Derived quantities have no thresholds and get an unset one.
+    pub fn get(&self, unit: Unit) -> Threshold {
+        match unit {
+            Unit::Temperature => self.temperature,
+            Unit::Humidity => self.humidity,
+            Unit::Pressure => self.pressure,
+            _ => Threshold::default(),
+        }
+    }
+
+    pub fn get_mut(&mut self, unit: Unit) -> Option<&mut Threshold> {
+        match unit {
+            Unit::Temperature => Some(&mut self.temperature),
+            Unit::Humidity => Some(&mut self.humidity),
+            Unit::Pressure => Some(&mut self.pressure),
+            _ => None,
+        }
+    }
+}
+
+/// Correction of a single unit in raw sensor units, either an offset or a
+/// line through two points.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Calibration {
+    pub offset: f64,
+    /// two pairs of the value read by the sensor and the value it should
+    /// have read, replaces the offset
+    pub two_point: Option<[(f64, f64); 2]>,
+}
+
+impl Calibration {
+    pub fn is_set(&self) -> bool {
+        self.offset != 0. || self.two_point.is_some()
+    }
+
+    pub fn apply(&self, value: f64) -> f64 {
+        match self.two_point {
+            Some([(read1, actual1), (read2, actual2)]) if read1 != read2 => {
+                actual1 + (value - read1) * (actual2 - actual1) / (read2 - read1)
+            }
+            _ => value + self.offset,
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Calibrations {
+    pub temperature: Calibration,
+    pub humidity: Calibration,
+    pub pressure: Calibration,
+}
+
+impl Calibrations {
+    pub fn is_set(&self) -> bool {
+        self.temperature.is_set() || self.humidity.is_set() || self.pressure.is_set()
+    }
+
+    /// Derived quantities are computed from the calibrated measurements
+    /// and get no calibration of their own.
+    pub fn get(&self, unit: Unit) -> Calibration {
+        match unit {
+            Unit::Temperature => self.temperature,
+            Unit::Humidity => self.humidity,
+            Unit::Pressure => self.pressure,
+            _ => Calibration::default(),
+        }
+    }
+
+    pub fn get_mut(&mut self, unit: Unit) -> Option<&mut Calibration> {
+        match unit {
+            Unit::Temperature => Some(&mut self.temperature),
+            Unit::Humidity => Some(&mut self.humidity),
+            Unit::Pressure => Some(&mut self.pressure),
+            _ => None,
+        }
+    }
+}
+
+/// Time to wait after a change before writing the settings, so a spin
+/// button held down doesn't rewrite the file on every step.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// [`SensorSettings`] of every sensor, saved as json in the user data dir.
+/// Clones share the same settings.
+#[derive(Clone)]
+pub struct LocalStore(Rc<RefCell<Inner>>);
+
+struct Inner {
+    path: PathBuf,
+    sensors: HashMap<Uuid, SensorSettings>,
+    pending_save: Option<glib::SourceId>,
+}
+
+impl LocalStore {
+    pub fn load() -> Self {
+        Self::open(glib::user_data_dir().join("ble-ws-ui").join("sensors.json"))
+    }
+
+    /// Read the settings stored at `path`, starting out empty if there are none.
+    pub fn open(path: PathBuf) -> Self {
+        let sensors = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content).unwrap_or_else(|e| {
+                tracing::error!("Invalid sensor settings in {}: {}", path.display(), e);
+                Default::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Default::default(),
+            Err(e) => {
+                tracing::error!("Could not read {}: {}", path.display(), e);
+                Default::default()
+            }
+        };
+
+        Self(Rc::new(RefCell::new(Inner {
+            path,
+            sensors,
+            pending_save: None,
+        })))
+    }
+
+    pub fn get(&self, id: Uuid) -> SensorSettings {
+        self.0
+            .borrow()
+            .sensors
+            .get(&id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Change the settings of sensor `id`, returns the new settings.
+    /// They are saved once no other change followed for [`SAVE_DELAY`].
+    pub fn update<F>(&self, id: Uuid, f: F) -> SensorSettings
+    where
+        F: FnOnce(&mut SensorSettings),
+    {
+        let ret = {
+            let mut inner = self.0.borrow_mut();
+            let settings = inner.sensors.entry(id).or_default();
+            f(settings);
+            settings.clone()
+        };
+        self.save_later();
+        ret
+    }
+
+    /// Write the metadata of every sensor that has some and isn't hidden to `path`.
+    pub fn export_metadata(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let inner = self.0.borrow();
+        let metadata = inner
+            .sensors
+            .iter()
+            .filter(|(_, settings)| !settings.hidden && !settings.metadata.is_empty())
+            .map(|(id, settings)| (*id, &settings.metadata))
+            .collect::<HashMap<_, _>>();
+        std::fs::write(path, serde_json::to_vec_pretty(&metadata)?)?;
+        Ok(())
+    }
+
+    /// Replace the metadata of the sensors listed in the file at `path`,
+    /// returns the new settings of the sensors that changed. Nothing changes
+    /// if the result can't be saved.
+    pub fn import_metadata(
+        &self,
+        path: &Path,
+    ) -> Result<Vec<(Uuid, SensorSettings)>, anyhow::Error> {
+        let metadata: HashMap<Uuid, SensorMetadata> =
+            serde_json::from_slice(&std::fs::read(path)?)?;
+        let mut sensors = self.0.borrow().sensors.clone();
+        let mut changed = Vec::new();
+        for (id, metadata) in metadata {
+            let settings = sensors.entry(id).or_default();
+            if settings.metadata != metadata {
+                settings.metadata = metadata;
+                changed.push((id, settings.clone()));
+            }
+        }
+        save(&self.0.borrow().path, &sensors)?;
+        // the saved copy includes any change still waiting to be saved
+        self.cancel_save();
+        self.0.borrow_mut().sensors = sensors;
+        Ok(changed)
+    }
+
+    /// Save right away if there are unsaved changes, e.g. before quitting.
+    pub fn flush(&self) {
+        if self.cancel_save() {
+            if let Err(e) = self.0.borrow().save() {
+                tracing::error!("Failed saving sensor settings: {}", e);
+            }
+        }
+    }
+
+    /// (Re)start the timer to save after the last change.
+    fn save_later(&self) {
+        self.cancel_save();
+        let inner = Rc::downgrade(&self.0);
+        let source = glib::timeout_add_local(SAVE_DELAY, move || {
+            if let Some(inner) = inner.upgrade() {
+                let mut inner = inner.borrow_mut();
+                // the source is done once this returns, it must not be removed anymore
+                inner.pending_save = None;
+                if let Err(e) = inner.save() {
+                    tracing::error!("Failed saving sensor settings: {}", e);
+                }
+            }
+            glib::Continue(false)
+        });
+        self.0.borrow_mut().pending_save = Some(source);
+    }
+
+    /// Stop a pending save, returns whether there was one.
+    fn cancel_save(&self) -> bool {
+        match self.0.borrow_mut().pending_save.take() {
+            Some(source) => {
+                source.remove();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Inner {
+    fn save(&self) -> Result<(), anyhow::Error> {
+        save(&self.path, &self.sensors)
+    }
+}
+
+fn save(path: &Path, sensors: &HashMap<Uuid, SensorSettings>) -> Result<(), anyhow::Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // write to a temporary file first so a crash can't leave half a file behind
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(sensors)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty directory for the files of a test, removed again when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("ble-ws-ui-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Store saved at `path` that starts out with `sensors`.
+    fn store(path: PathBuf, sensors: Vec<(Uuid, SensorSettings)>) -> LocalStore {
+        let sensors = sensors.into_iter().collect::<HashMap<_, _>>();
+        std::fs::write(&path, serde_json::to_vec(&sensors).unwrap()).unwrap();
+        LocalStore::open(path)
+    }
+
+    fn metadata(room: &str, tags: &[&str]) -> SensorMetadata {
+        SensorMetadata {
+            room: room.to_owned(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn calibration() {
+        let offset = Calibration {
+            offset: 50.,
+            two_point: None,
+        };
+        let two_point = Calibration {
+            offset: 0.,
+            two_point: Some([(1000., 1100.), (3000., 3300.)]),
+        };
+        for (calibration, value, expected) in [
+            (Calibration::default(), 2000., 2000.),
+            (offset, 2000., 2050.),
+            (offset, -2000., -1950.),
+            (two_point, 1000., 1100.),
+            (two_point, 3000., 3300.),
+            (two_point, 2000., 2200.),
+            // extrapolated beyond the points
+            (two_point, 0., 0.),
+            (two_point, 4000., 4400.),
+            // the points replace the offset
+            (
+                Calibration {
+                    offset: 50.,
+                    ..two_point
+                },
+                2000.,
+                2200.,
+            ),
+            // swapped points describe the same line
+            (
+                Calibration {
+                    offset: 0.,
+                    two_point: Some([(3000., 3300.), (1000., 1100.)]),
+                },
+                2000.,
+                2200.,
+            ),
+            // the same reading twice gives no line, the offset is used instead
+            (
+                Calibration {
+                    offset: 30.,
+                    two_point: Some([(1000., 1100.), (1000., 1200.)]),
+                },
+                2000.,
+                2030.,
+            ),
+        ] {
+            assert_eq!(
+                calibration.apply(value),
+                expected,
+                "{:?} of {}",
+                calibration,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn metadata_round_trip() {
+        let dir = TempDir::new("round-trip");
+        let (kitchen, garden, neighbour, bare) = (
+            Uuid::from_u128(1),
+            Uuid::from_u128(2),
+            Uuid::from_u128(3),
+            Uuid::from_u128(4),
+        );
+        let kitchen_metadata = SensorMetadata {
+            notes: "next to the oven".to_owned(),
+            icon: Some("weather-clear-symbolic".to_owned()),
+            color: Some(SensorColor::Orange),
+            ..metadata("Kitchen", &["indoor", "ground floor"])
+        };
+        let garden_metadata = metadata("Garden", &["outdoor"]);
+        let source = store(
+            dir.0.join("source.json"),
+            vec![
+                (
+                    kitchen,
+                    SensorSettings {
+                        metadata: kitchen_metadata.clone(),
+                        altitude: 300.,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    garden,
+                    SensorSettings {
+                        metadata: garden_metadata.clone(),
+                        favorite: true,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    neighbour,
+                    SensorSettings {
+                        metadata: metadata("Next door", &[]),
+                        hidden: true,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    bare,
+                    SensorSettings {
+                        altitude: 300.,
+                        ..Default::default()
+                    },
+                ),
+            ],
+        );
+        let file = dir.0.join("metadata.json");
+        source.export_metadata(&file).unwrap();
+
+        let target = LocalStore::open(dir.0.join("target.json"));
+        let mut changed = target.import_metadata(&file).unwrap();
+        changed.sort_by_key(|(id, _)| *id);
+        let metadata_of = |id| SensorSettings {
+            metadata: match id {
+                id if id == kitchen => kitchen_metadata.clone(),
+                _ => garden_metadata.clone(),
+            },
+            ..Default::default()
+        };
+        // only the metadata of visible sensors is shared
+        assert_eq!(
+            changed,
+            vec![
+                (kitchen, metadata_of(kitchen)),
+                (garden, metadata_of(garden))
+            ]
+        );
+        for id in [neighbour, bare] {
+            assert_eq!(target.get(id), SensorSettings::default());
+        }
+
+        // the imported metadata is saved right away
+        let reopened = LocalStore::open(dir.0.join("target.json"));
+        for id in [kitchen, garden] {
+            assert_eq!(reopened.get(id), metadata_of(id));
+        }
+    }
+
+    #[test]
+    fn import_merges() {
+        let dir = TempDir::new("merge");
+        let (listed, unchanged, unlisted, new) = (
+            Uuid::from_u128(1),
+            Uuid::from_u128(2),
+            Uuid::from_u128(3),
+            Uuid::from_u128(4),
+        );
+        let path = dir.0.join("sensors.json");
+        let local = store(
+            path.clone(),
+            vec![
+                (
+                    listed,
+                    SensorSettings {
+                        metadata: metadata("Kitchen", &[]),
+                        altitude: 300.,
+                        favorite: true,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    unchanged,
+                    SensorSettings {
+                        metadata: metadata("Garden", &["outdoor"]),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    unlisted,
+                    SensorSettings {
+                        metadata: metadata("Attic", &[]),
+                        hidden: true,
+                        ..Default::default()
+                    },
+                ),
+            ],
+        );
+        let before = [listed, unchanged, unlisted]
+            .iter()
+            .map(|&id| local.get(id))
+            .collect::<Vec<_>>();
+
+        let file = dir.0.join("metadata.json");
+        let imported = vec![
+            (listed, metadata("Living room", &["indoor"])),
+            (unchanged, metadata("Garden", &["outdoor"])),
+            (new, metadata("Cellar", &[])),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+        std::fs::write(&file, serde_json::to_vec(&imported).unwrap()).unwrap();
+
+        let mut changed = local.import_metadata(&file).unwrap();
+        changed.sort_by_key(|(id, _)| *id);
+        // the metadata gets replaced, the other settings are kept
+        let listed_settings = SensorSettings {
+            metadata: metadata("Living room", &["indoor"]),
+            ..before[0].clone()
+        };
+        let new_settings = SensorSettings {
+            metadata: metadata("Cellar", &[]),
+            ..Default::default()
+        };
+        assert_eq!(
+            changed,
+            vec![
+                (listed, listed_settings.clone()),
+                (new, new_settings.clone())
+            ]
+        );
+        // sensors missing from the file are left alone
+        assert_eq!(local.get(unchanged), before[1]);
+        assert_eq!(local.get(unlisted), before[2]);
+
+        let reopened = LocalStore::open(path);
+        for (id, settings) in [
+            (listed, listed_settings),
+            (unchanged, before[1].clone()),
+            (unlisted, before[2].clone()),
+            (new, new_settings),
+        ] {
+            assert_eq!(reopened.get(id), settings);
+        }
+
+        // a broken file changes nothing
+        std::fs::write(&file, b"{").unwrap();
+        assert!(local.import_metadata(&file).is_err());
+        assert!(local.import_metadata(&dir.0.join("missing.json")).is_err());
+        assert_eq!(local.get(unlisted), before[2]);
+    }
+
+    #[test]
+    fn import_save_failure() {
+        let dir = TempDir::new("save-failure");
+        let id = Uuid::from_u128(1);
+        let path = dir.0.join("sensors.json");
+        let kitchen = SensorSettings {
+            metadata: metadata("Kitchen", &[]),
+            ..Default::default()
+        };
+        let local = store(path.clone(), vec![(id, kitchen.clone())]);
+
+        let file = dir.0.join("metadata.json");
+        let imported = vec![(id, metadata("Living room", &[]))]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        std::fs::write(&file, serde_json::to_vec(&imported).unwrap()).unwrap();
+        // the temporary file can't be written in place of a directory
+        std::fs::create_dir(path.with_extension("json.tmp")).unwrap();
+
+        assert!(local.import_metadata(&file).is_err());
+        // neither applied in memory nor saved later on
+        assert_eq!(local.get(id), kitchen);
+        local.flush();
+        assert_eq!(LocalStore::open(path).get(id), kitchen);
+    }
+}
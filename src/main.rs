@@ -1,10 +1,20 @@
+mod alerts;
 mod application;
+mod autostart;
 mod config;
 mod data;
+mod deep_link;
 mod event_loop;
+mod forecast;
+mod local_store;
+mod physics;
+mod search;
+mod sensor_group;
 mod sensor_name_filter;
 mod sensor_obj;
+mod sensor_sorter;
 mod services;
+mod units;
 mod util;
 mod widgets;
 
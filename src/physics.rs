@@ -0,0 +1,201 @@
+//! Quantities derived from temperature in °C, relative humidity in % and
+//! pressure in hPa.
+
+/// Magnus formula coefficients over water (Sonntag 1990).
+const MAGNUS_B: f64 = 17.62;
+const MAGNUS_C: f64 = 243.12;
+
+/// Temperature lapse rate of the standard atmosphere in K/m.
+const LAPSE_RATE: f64 = 0.0065;
+
+/// Exponent of the barometric formula, g·M / (R·L).
+const BAROMETRIC_EXPONENT: f64 = 5.257;
+
+/// Sea level temperature of the standard atmosphere in K.
+const STANDARD_TEMPERATURE: f64 = 288.15;
+
+/// Below this the logarithm in the dew point goes to infinity, sensors
+/// don't get that dry anyway.
+const MIN_HUMIDITY: f64 = 0.1;
+
+/// Saturation vapour pressure over water in hPa.
+pub fn saturation_vapour_pressure(celsius: f64) -> f64 {
+    6.112 * (MAGNUS_B * celsius / (MAGNUS_C + celsius)).exp()
+}
+
+/// Temperature in °C the air has to be cooled to for water to condense.
+pub fn dew_point(celsius: f64, humidity: f64) -> f64 {
+    let humidity = humidity.clamp(MIN_HUMIDITY, 100.);
+    let gamma = (humidity / 100.).ln() + MAGNUS_B * celsius / (MAGNUS_C + celsius);
+    MAGNUS_C * gamma / (MAGNUS_B - gamma)
+}
+
+/// Mass of water vapour per volume of air in g/m³.
+pub fn absolute_humidity(celsius: f64, humidity: f64) -> f64 {
+    let vapour_pressure = saturation_vapour_pressure(celsius) * humidity / 100.;
+    // hPa to Pa over the specific gas constant of water vapour, in g
+    216.7 * vapour_pressure / (celsius + 273.15)
+}
+
+/// Apparent temperature in °C after the NWS heat index, which is only
+/// meaningful in warm weather and close to the air temperature otherwise.
+pub fn heat_index(celsius: f64, humidity: f64) -> f64 {
+    let t = celsius * 9. / 5. + 32.;
+    let rh = humidity.clamp(0., 100.);
+
+    let simple = 0.5 * (t + 61. + (t - 68.) * 1.2 + rh * 0.094);
+    let fahrenheit = if (simple + t) / 2. < 80. {
+        simple
+    } else {
+        // Rothfusz regression
+        let mut hi = -42.379 + 2.049_015_23 * t + 10.143_331_27 * rh
+            - 0.224_755_41 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+        if rh < 13. && (80. ..=112.).contains(&t) {
+            hi -= (13. - rh) / 4. * ((17. - (t - 95.).abs()) / 17.).sqrt();
+        } else if rh > 85. && (80. ..=87.).contains(&t) {
+            hi += (rh - 85.) / 10. * (87. - t) / 5.;
+        }
+        hi
+    };
+    (fahrenheit - 32.) * 5. / 9.
+}
+
+/// How much more water vapour the air could hold in kPa, what plants react to.
+pub fn vapour_pressure_deficit(celsius: f64, humidity: f64) -> f64 {
+    let humidity = humidity.clamp(0., 100.);
+    saturation_vapour_pressure(celsius) * (1. - humidity / 100.) / 10.
+}
+
+/// Pressure in hPa measured at `altitude` m reduced to sea level.
+///
+/// Without a `reference_temperature` in °C at the sensor the standard
+/// atmosphere is assumed, like the QNH given by weather services.
+pub fn sea_level_pressure(pressure: f64, altitude: f64, reference_temperature: Option<f64>) -> f64 {
+    let height = LAPSE_RATE * altitude;
+    let base = match reference_temperature {
+        Some(celsius) => 1. - height / (celsius + height + 273.15),
+        None => 1. - height / STANDARD_TEMPERATURE,
+    };
+    pressure * base.powf(-BAROMETRIC_EXPONENT)
+}
+
+#[cfg(test)]
+mod tests {
+    fn assert_close(actual: f64, expected: f64, tolerance: f64, what: &str) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{}: expected {} ± {}, got {}",
+            what,
+            expected,
+            tolerance,
+            actual
+        );
+    }
+
+    #[test]
+    fn dew_point() {
+        // (°C, %, dew point °C)
+        let cases = [
+            (20., 50., 9.26),
+            (25., 60., 16.69),
+            (0., 80., -3.04),
+            (-10., 70., -14.44),
+            (30., 100., 30.),
+        ];
+        for &(celsius, humidity, expected) in &cases {
+            assert_close(
+                super::dew_point(celsius, humidity),
+                expected,
+                0.05,
+                &format!("dew point at {}°C {}%", celsius, humidity),
+            );
+        }
+        assert!(super::dew_point(20., 0.).is_finite());
+    }
+
+    #[test]
+    fn absolute_humidity() {
+        // (°C, %, g/m³)
+        let cases = [
+            (20., 50., 8.62),
+            (25., 60., 13.81),
+            (0., 100., 4.85),
+            (30., 0., 0.),
+        ];
+        for &(celsius, humidity, expected) in &cases {
+            assert_close(
+                super::absolute_humidity(celsius, humidity),
+                expected,
+                0.05,
+                &format!("absolute humidity at {}°C {}%", celsius, humidity),
+            );
+        }
+    }
+
+    #[test]
+    fn heat_index() {
+        // (°C, %, heat index °C), warm cases from the NWS heat index table
+        let cases = [
+            (20., 50., 19.36),
+            (26.67, 40., 26.7),
+            (32.22, 70., 41.1),
+            (37.78, 50., 47.8),
+        ];
+        for &(celsius, humidity, expected) in &cases {
+            assert_close(
+                super::heat_index(celsius, humidity),
+                expected,
+                0.6,
+                &format!("heat index at {}°C {}%", celsius, humidity),
+            );
+        }
+    }
+
+    #[test]
+    fn vapour_pressure_deficit() {
+        // (°C, %, kPa)
+        let cases = [
+            (25., 60., 1.26),
+            (20., 50., 1.17),
+            (30., 100., 0.),
+            (10., 0., 1.23),
+        ];
+        for &(celsius, humidity, expected) in &cases {
+            assert_close(
+                super::vapour_pressure_deficit(celsius, humidity),
+                expected,
+                0.01,
+                &format!("vapour pressure deficit at {}°C {}%", celsius, humidity),
+            );
+        }
+    }
+
+    #[test]
+    fn sea_level_pressure() {
+        // (hPa, m, °C, sea level hPa)
+        let cases = [
+            (1013.25, 0., None, 1013.25),
+            (1000., 0., Some(25.), 1000.),
+            (954.6, 500., None, 1013.2),
+            (898.76, 1000., None, 1013.25),
+            (954.6, 500., Some(15.), 1012.5),
+            (954.6, 500., Some(-10.), 1018.3),
+        ];
+        for &(pressure, altitude, temperature, expected) in &cases {
+            assert_close(
+                super::sea_level_pressure(pressure, altitude, temperature),
+                expected,
+                0.3,
+                &format!(
+                    "sea level pressure of {} hPa at {} m and {:?}°C",
+                    pressure, altitude, temperature
+                ),
+            );
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! The sensor search, e.g. `kitchen temp>25 disconnected`. Every word of a
+//! search has to match: text is looked for in the label, id, room and tags,
+//! `<unit><op><value>` compares a current value in the displayed units.
+
+use crate::units::Unit;
+use std::ops::Range;
+
+/// Shortest prefix of a unit name that can be used in a value predicate.
+const MIN_UNIT_PREFIX: usize = 3;
+
+/// Shortest text that gets matched fuzzily against the label.
+const MIN_FUZZY_LEN: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    fn holds(self, value: f64, limit: f64) -> bool {
+        match self {
+            Comparison::Less => value < limit,
+            Comparison::LessEqual => value <= limit,
+            Comparison::Greater => value > limit,
+            Comparison::GreaterEqual => value >= limit,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+    /// lowercase text
+    Text(String),
+    Value {
+        unit: Unit,
+        comparison: Comparison,
+        value: f64,
+    },
+    Connected(bool),
+}
+
+/// What a sensor can be found by.
+#[derive(Debug, Default)]
+pub struct Searchable<'a> {
+    pub label: Option<&'a str>,
+    pub id: &'a str,
+    pub room: &'a str,
+    pub tags: &'a [String],
+    pub connected: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    pub fn parse(s: &str) -> Self {
+        Self {
+            terms: s.split_whitespace().map(parse_term).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Whether `sensor` matches every term, `value` gives the current value
+    /// of a unit in the displayed units.
+    pub fn matches(&self, sensor: &Searchable, value: impl Fn(Unit) -> f64) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Text(text) => text_matches(sensor, text),
+            Term::Value {
+                unit,
+                comparison,
+                value: limit,
+            } => sensor.connected && comparison.holds(value(*unit), *limit),
+            Term::Connected(connected) => sensor.connected == *connected,
+        })
+    }
+
+    /// Sorted, non overlapping byte ranges of `text` matched by the text
+    /// terms, the label falls back to the fuzzy matches.
+    pub fn highlights(&self, text: &str, fuzzy: bool) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        for term in &self.terms {
+            if let Term::Text(needle) = term {
+                let found = find(text, needle);
+                if found.is_empty() && fuzzy {
+                    ranges.extend(fuzzy_find(text, needle).unwrap_or_default());
+                } else {
+                    ranges.extend(found);
+                }
+            }
+        }
+
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+fn parse_term(word: &str) -> Term {
+    let word = word.to_lowercase();
+    match word.as_str() {
+        "connected" | "online" => return Term::Connected(true),
+        "disconnected" | "offline" => return Term::Connected(false),
+        _ => (),
+    }
+    parse_value(&word).unwrap_or(Term::Text(word))
+}
+
+/// `temp>25`, `hum<=30.5` or `pres>1000,5`.
+fn parse_value(word: &str) -> Option<Term> {
+    let op = word.find(|c| c == '<' || c == '>')?;
+    let (name, rest) = word.split_at(op);
+    let (comparison, value) = if let Some(value) = rest.strip_prefix("<=") {
+        (Comparison::LessEqual, value)
+    } else if let Some(value) = rest.strip_prefix(">=") {
+        (Comparison::GreaterEqual, value)
+    } else if let Some(value) = rest.strip_prefix('<') {
+        (Comparison::Less, value)
+    } else {
+        (Comparison::Greater, &rest[1..])
+    };
+
+    if name.len() < MIN_UNIT_PREFIX {
+        return None;
+    }
+    let unit = Unit::ALL
+        .iter()
+        .copied()
+        .find(|unit| unit.name().starts_with(name))?;
+    // accept the decimal separator of the locale as well
+    let value = value.replace(',', ".").parse::<f64>().ok()?;
+    Some(Term::Value {
+        unit,
+        comparison,
+        value,
+    })
+}
+
+fn text_matches(sensor: &Searchable, text: &str) -> bool {
+    let label = sensor.label.unwrap_or_default();
+    !find(label, text).is_empty()
+        || fuzzy_find(label, text).is_some()
+        || sensor.id.starts_with(text)
+        || !find(sensor.room, text).is_empty()
+        || sensor.tags.iter().any(|tag| !find(tag, text).is_empty())
+}
+
+/// Byte ranges of every case insensitive occurrence of the lowercase
+/// `needle` in `haystack`.
+fn find(haystack: &str, needle: &str) -> Vec<Range<usize>> {
+    let mut ret = Vec::new();
+    if needle.is_empty() {
+        return ret;
+    }
+    for (start, _) in haystack.char_indices() {
+        let mut chars = haystack[start..].chars();
+        let mut end = start;
+        let found = needle.chars().all(|n| match chars.next() {
+            Some(c) if c.to_lowercase().eq(n.to_lowercase()) => {
+                end += c.len_utf8();
+                true
+            }
+            _ => false,
+        });
+        if found {
+            ret.push(start..end);
+        }
+    }
+    ret
+}
+
+/// Byte ranges of the characters of the lowercase `needle` appearing in
+/// order in `haystack`, like `ktchn` in `Kitchen`.
+fn fuzzy_find(haystack: &str, needle: &str) -> Option<Vec<Range<usize>>> {
+    if needle.chars().count() < MIN_FUZZY_LEN {
+        return None;
+    }
+    let mut ret = Vec::new();
+    let mut chars = haystack.char_indices();
+    for n in needle.chars() {
+        let (i, c) = chars
+            .by_ref()
+            .find(|(_, c)| c.to_lowercase().eq(n.to_lowercase()))?;
+        ret.push(i..i + c.len_utf8());
+    }
+    Some(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Query, Searchable};
+    use crate::units::Unit;
+
+    fn sensor<'a>(label: Option<&'a str>, tags: &'a [String]) -> Searchable<'a> {
+        Searchable {
+            label,
+            id: "6ba7b810-9dad-11d1-80b4-00c04fd430c8",
+            room: "Living room",
+            tags,
+            connected: true,
+        }
+    }
+
+    #[test]
+    fn text() {
+        let tags = ["north".to_owned(), "outdoor".to_owned()];
+        let kitchen = sensor(Some("Kitchen Window"), &tags);
+        let matches = |query: &str| Query::parse(query).matches(&kitchen, |_| 0.);
+
+        assert!(matches(""));
+        assert!(matches("kitchen"));
+        assert!(matches("WINDOW"));
+        assert!(matches("chen"));
+        assert!(matches("ktchn"));
+        assert!(matches("6ba7"));
+        assert!(matches("living"));
+        assert!(matches("outdoor"));
+        assert!(matches("kitchen north"));
+        assert!(!matches("bedroom"));
+        assert!(!matches("kitchen bedroom"));
+        // ids only match from the start
+        assert!(!matches("9dad"));
+        // too short to match fuzzily
+        assert!(!matches("kw"));
+
+        // sensors without a label can be found by their id
+        let unlabeled = sensor(None, &[]);
+        assert!(Query::parse("6ba7b810").matches(&unlabeled, |_| 0.));
+    }
+
+    #[test]
+    fn values() {
+        let mut sensor = sensor(Some("Kitchen"), &[]);
+        let value = |unit: Unit| match unit {
+            Unit::Temperature => 25.5,
+            Unit::Humidity => 30.,
+            _ => 1013.,
+        };
+        let matches = |query: &str, sensor: &Searchable| Query::parse(query).matches(sensor, value);
+
+        assert!(matches("temp>25", &sensor));
+        assert!(!matches("temperature>25,5", &sensor));
+        assert!(matches("temp>=25,5", &sensor));
+        assert!(matches("hum<=30", &sensor));
+        assert!(!matches("hum<30", &sensor));
+        assert!(matches("pres>1000 kitchen", &sensor));
+        assert!(matches("connected", &sensor));
+        assert!(!matches("disconnected", &sensor));
+
+        sensor.connected = false;
+        assert!(matches("disconnected", &sensor));
+        assert!(!matches("temp>25", &sensor));
+
+        // not a predicate, searched as text
+        assert!(!matches("te>25", &sensor));
+        assert!(!matches("temp>warm", &sensor));
+    }
+
+    #[test]
+    fn highlights() {
+        let query = Query::parse("kit en");
+        assert_eq!(query.highlights("Kitchen", true), vec![0..3, 5..7]);
+        assert_eq!(
+            Query::parse("ktn").highlights("Kitchen", true),
+            vec![0..1, 2..3, 6..7]
+        );
+        assert!(Query::parse("ktn").highlights("Kitchen", false).is_empty());
+        // overlapping matches are merged
+        assert_eq!(
+            Query::parse("kitc tch").highlights("Kitchen", true),
+            vec![0..5]
+        );
+        assert_eq!(Query::parse("temp>20").highlights("Kitchen", true), vec![]);
+    }
+}
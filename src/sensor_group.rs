@@ -0,0 +1,202 @@
+use glib::subclass::prelude::*;
+use gtk::prelude::*;
+
+use crate::{local_store::SensorMetadata, sensor_obj::SensorObj, units::Unit};
+
+/// How the sensors of the overview are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    None,
+    Room,
+    Tag,
+}
+
+impl Default for Grouping {
+    fn default() -> Self {
+        Grouping::None
+    }
+}
+
+impl Grouping {
+    /// In the order of the overview dropdown.
+    pub const ALL: [Grouping; 3] = [Grouping::None, Grouping::Room, Grouping::Tag];
+
+    /// Name used in the settings.
+    pub fn nick(self) -> &'static str {
+        match self {
+            Grouping::None => "none",
+            Grouping::Room => "room",
+            Grouping::Tag => "tag",
+        }
+    }
+
+    pub fn from_nick(nick: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|grouping| grouping.nick() == nick)
+    }
+
+    /// Groups a sensor with `metadata` belongs to, the empty key collects
+    /// the sensors without a room or tags.
+    pub fn keys(self, metadata: &SensorMetadata) -> Vec<String> {
+        match self {
+            Grouping::None => Vec::new(),
+            Grouping::Room => vec![metadata.room.clone()],
+            Grouping::Tag if metadata.tags.is_empty() => vec![String::new()],
+            Grouping::Tag => metadata.tags.clone(),
+        }
+    }
+
+    fn title(self, key: &str) -> String {
+        match (self, key.is_empty()) {
+            (Grouping::Room, true) => "No room".to_owned(),
+            (Grouping::Tag, true) => "No tags".to_owned(),
+            _ => key.to_owned(),
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+    use once_cell::unsync::OnceCell;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Default)]
+    pub struct SensorGroup {
+        pub grouping: Cell<Grouping>,
+        pub key: RefCell<String>,
+        pub filter: OnceCell<gtk::CustomFilter>,
+        pub model: OnceCell<gtk::FilterListModel>,
+        pub summary: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SensorGroup {
+        const NAME: &'static str = "BleWsSensorGroup";
+        type Type = super::SensorGroup;
+        type ParentType = glib::Object;
+        type Interfaces = ();
+    }
+
+    impl ObjectImpl for SensorGroup {
+        fn properties() -> &'static [glib::ParamSpec] {
+            use once_cell::sync::Lazy;
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpec::new_string(
+                        "title",
+                        "Title",
+                        "Title",
+                        None,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpec::new_string(
+                        "summary",
+                        "Summary",
+                        "Number of sensors and averages of their values",
+                        None,
+                        glib::ParamFlags::READABLE,
+                    ),
+                ]
+            });
+
+            PROPERTIES.as_ref()
+        }
+
+        fn property(&self, _obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "title" => self.grouping.get().title(&self.key.borrow()).to_value(),
+                "summary" => self.summary.borrow().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct SensorGroup(ObjectSubclass<imp::SensorGroup>);
+}
+
+impl SensorGroup {
+    /// Group of the `sensors` that have `key` with `grouping`.
+    pub fn new(grouping: Grouping, key: &str, sensors: &impl IsA<gio::ListModel>) -> Self {
+        let ret: Self = glib::Object::new(&[]).expect("Can't create SensorGroup");
+        let self_ = imp::SensorGroup::from_instance(&ret);
+        self_.grouping.set(grouping);
+        self_.key.replace(key.to_owned());
+
+        let key = key.to_owned();
+        let filter = gtk::CustomFilter::new(move |item| {
+            let sensor = item.downcast_ref::<SensorObj>().unwrap();
+            grouping.keys(&sensor.settings().metadata).contains(&key)
+        });
+        let model = gtk::FilterListModel::new(Some(sensors), Some(&filter));
+        model.connect_items_changed(glib::clone!(@weak ret => move |_, _, _, _| {
+            ret.update_summary();
+        }));
+        self_.filter.set(filter).unwrap();
+        self_.model.set(model).unwrap();
+        ret.update_summary();
+        ret
+    }
+
+    pub fn key(&self) -> String {
+        let self_ = imp::SensorGroup::from_instance(self);
+        self_.key.borrow().clone()
+    }
+
+    /// Sensors in the group.
+    pub fn model(&self) -> gio::ListModel {
+        let self_ = imp::SensorGroup::from_instance(self);
+        self_.model.get().unwrap().clone().upcast()
+    }
+
+    /// Check the membership of the sensors again after their metadata changed.
+    pub fn refilter(&self) {
+        let self_ = imp::SensorGroup::from_instance(self);
+        self_
+            .filter
+            .get()
+            .unwrap()
+            .changed(gtk::FilterChange::Different);
+    }
+
+    /// Recompute the number of sensors and the average values.
+    pub fn update_summary(&self) {
+        let self_ = imp::SensorGroup::from_instance(self);
+        let model = self.model();
+        let sensors = (0..model.n_items())
+            .filter_map(|i| model.item(i)?.downcast::<SensorObj>().ok())
+            .collect::<Vec<_>>();
+        let connected = sensors
+            .iter()
+            .filter(|sensor| sensor.data().connected.get())
+            .map(|sensor| sensor.values())
+            .collect::<Vec<_>>();
+
+        let mut parts = vec![match sensors.len() {
+            1 => "1 sensor".to_owned(),
+            n => format!("{} sensors", n),
+        }];
+        if !connected.is_empty() {
+            for unit in &[Unit::Temperature, Unit::Humidity] {
+                let average = connected
+                    .iter()
+                    .map(|values| values.get(*unit))
+                    .sum::<f64>()
+                    / connected.len() as f64;
+                parts.push(format!("⌀ {}", unit.format_value(average)));
+            }
+        }
+        if connected.len() < sensors.len() {
+            parts.push(format!("{} disconnected", sensors.len() - connected.len()));
+        }
+
+        let summary = parts.join(" · ");
+        if *self_.summary.borrow() != summary {
+            self_.summary.replace(summary);
+            self.notify("summary");
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use glib::subclass::prelude::*;
 use gtk::prelude::*;
 
+use crate::{
+    search::{Query, Searchable},
+    sensor_obj::SensorObj,
+    units::UnitSystem,
+};
+
 mod imp {
     use gtk::subclass::prelude::FilterImpl;
     use std::cell::RefCell;
@@ -10,6 +16,7 @@ mod imp {
     #[derive(Default)]
     pub struct SensorNameFilter {
         pub filter: RefCell<String>,
+        pub query: RefCell<Query>,
     }
 
     #[glib::object_subclass]
@@ -28,18 +35,25 @@ mod imp {
 
     impl FilterImpl for SensorNameFilter {
         fn match_(&self, _filter: &Self::Type, item: &glib::Object) -> bool {
-            let obj = item.downcast_ref::<crate::sensor_obj::SensorObj>().unwrap();
-            let filter = self.filter.borrow();
-            if filter.is_empty() {
+            let obj = item.downcast_ref::<SensorObj>().unwrap();
+            let query = self.query.borrow();
+            if query.is_empty() {
                 return true;
             }
-
             let label = obj.data().label.borrow();
-            if let Some(label) = &*label {
-                label.starts_with(&*filter)
-            } else {
-                false
-            }
+            let id = obj.id().to_string();
+            let metadata = obj.settings().metadata;
+            let sensor = Searchable {
+                label: label.as_deref(),
+                id: &id,
+                room: &metadata.room,
+                tags: &metadata.tags,
+                connected: obj.data().connected.get(),
+            };
+            let values = obj.values();
+            query.matches(&sensor, |unit| {
+                UnitSystem::current().convert(unit, values.get(unit))
+            })
         }
     }
 }
@@ -60,7 +74,22 @@ impl SensorNameFilter {
             my_filter.clear();
             my_filter.push_str(filter);
             drop(my_filter);
+            self_.query.replace(Query::parse(filter));
             self.changed(gtk::FilterChange::Different);
         }
     }
+
+    /// Filter again after values or metadata changed, if there is a search.
+    pub fn refilter(&self) {
+        let self_ = imp::SensorNameFilter::from_instance(self);
+        if !self_.query.borrow().is_empty() {
+            self.changed(gtk::FilterChange::Different);
+        }
+    }
+
+    /// The search the sensors are currently filtered by.
+    pub fn query(&self) -> Query {
+        let self_ = imp::SensorNameFilter::from_instance(self);
+        self_.query.borrow().clone()
+    }
 }
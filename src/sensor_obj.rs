@@ -3,7 +3,11 @@ use glib::subclass::prelude::*;
 use gtk::prelude::*;
 use uuid::Uuid;
 
-use crate::data::{SharedTimeseries, Timeseries, TimeseriesRow};
+use crate::{
+    data::{Corrections, SensorValues, SharedTimeseries, Timeseries, TimeseriesRow},
+    forecast::Tendency,
+    local_store::SensorSettings,
+};
 
 pub mod imp {
     use super::*;
@@ -19,6 +23,14 @@ pub mod imp {
         pub pressure: Cell<u32>,
         pub humidity: Cell<u32>,
         pub live_timeseries: RefCell<SharedTimeseries>,
+        /// whether the live timeseries covers the whole day and not only
+        /// the history needed for the tendency
+        pub live_complete: Cell<bool>,
+        pub settings: RefCell<SensorSettings>,
+        pub corrections: Cell<Corrections>,
+        pub tendency: Cell<Option<Tendency>>,
+        /// time of the last values received, 0 if none were
+        pub last_update: Cell<u32>,
     }
 
     #[glib::object_subclass]
@@ -89,6 +101,36 @@ pub mod imp {
                         crate::data::SharedTimeseries::static_type(),
                         glib::ParamFlags::READABLE,
                     ),
+                    glib::ParamSpec::new_boxed(
+                        "settings",
+                        "Settings",
+                        "Local settings",
+                        SensorSettings::static_type(),
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpec::new_boxed(
+                        "corrections",
+                        "Corrections",
+                        "Corrections applied to the values",
+                        Corrections::static_type(),
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpec::new_boxed(
+                        "tendency",
+                        "Tendency",
+                        "Pressure tendency of the live timeseries",
+                        Tendency::static_type(),
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpec::new_uint(
+                        "last-update",
+                        "Last update",
+                        "Unix timestamp of the last values received",
+                        u32::MIN,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READABLE,
+                    ),
                 ]
             });
 
@@ -153,6 +195,10 @@ pub mod imp {
                 "humidity" => self.humidity.get().to_value(),
                 "pressure" => self.pressure.get().to_value(),
                 "live-timeseries" => self.live_timeseries.borrow().to_value(),
+                "settings" => self.settings.borrow().to_value(),
+                "corrections" => self.corrections.get().to_value(),
+                "tendency" => self.tendency.get().to_value(),
+                "last-update" => self.last_update.get().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -184,12 +230,107 @@ impl SensorObj {
         self_.live_timeseries.borrow().clone()
     }
 
+    /// Whether the whole live timeseries was fetched already.
+    pub fn has_live_timeseries(&self) -> bool {
+        let self_ = imp::SensorObj::from_instance(self);
+        self_.live_complete.get()
+    }
+
     pub fn set_timeseries(&self, timeseries: Timeseries) {
         let self_ = imp::SensorObj::from_instance(self);
+        self_.live_complete.set(true);
+        self.replace_timeseries(timeseries);
+    }
+
+    /// Use the few hours of history fetched for the tendency until the
+    /// whole live timeseries is needed.
+    pub fn set_tendency_timeseries(&self, timeseries: Timeseries) {
+        if !self.has_live_timeseries() {
+            self.replace_timeseries(timeseries);
+        }
+    }
+
+    fn replace_timeseries(&self, mut timeseries: Timeseries) {
+        let self_ = imp::SensorObj::from_instance(self);
+        timeseries.set_corrections(self_.corrections.get());
         self_
             .live_timeseries
             .replace(SharedTimeseries::new(timeseries));
         self.notify("live-timeseries");
+        self.update_tendency();
+    }
+
+    pub fn settings(&self) -> SensorSettings {
+        let self_ = imp::SensorObj::from_instance(self);
+        self_.settings.borrow().clone()
+    }
+
+    /// Same as `settings().hidden` without copying the settings.
+    pub fn is_hidden(&self) -> bool {
+        let self_ = imp::SensorObj::from_instance(self);
+        self_.settings.borrow().hidden
+    }
+
+    /// Same as `settings().favorite` without copying the settings.
+    pub fn is_favorite(&self) -> bool {
+        let self_ = imp::SensorObj::from_instance(self);
+        self_.settings.borrow().favorite
+    }
+
+    pub fn set_settings(&self, settings: SensorSettings) {
+        let self_ = imp::SensorObj::from_instance(self);
+        if *self_.settings.borrow() != settings {
+            self_.settings.replace(settings);
+            self.notify("settings");
+            // the forecast depends on the altitude
+            self.update_tendency();
+        }
+    }
+
+    fn update_tendency(&self) {
+        let self_ = imp::SensorObj::from_instance(self);
+        let tendency = match &*self_.live_timeseries.borrow().0 {
+            Some(timeseries) => timeseries
+                .borrow()
+                .pressure_tendency(&self_.settings.borrow()),
+            None => None,
+        };
+        if self_.tendency.replace(tendency) != tendency {
+            self.notify("tendency");
+        }
+    }
+
+    pub fn corrections(&self) -> Corrections {
+        let self_ = imp::SensorObj::from_instance(self);
+        self_.corrections.get()
+    }
+
+    /// Correct the current values and the live timeseries with `corrections`.
+    pub fn set_corrections(&self, corrections: Corrections) {
+        let self_ = imp::SensorObj::from_instance(self);
+        if self_.corrections.replace(corrections) == corrections {
+            return;
+        }
+        if let Some(timeseries) = &*self_.live_timeseries.borrow().0 {
+            timeseries.borrow_mut().set_corrections(corrections);
+        }
+        self.notify("corrections");
+        // rerun the transforms of the sensor row bindings
+        self.notify("temperature");
+        self.notify("humidity");
+        self.notify("pressure");
+        self.notify("live-timeseries");
+    }
+
+    /// Current values with the corrections of the sensor.
+    pub fn values(&self) -> SensorValues {
+        let self_ = imp::SensorObj::from_instance(self);
+        SensorValues {
+            temperature: self_.temperature.get(),
+            humidity: self_.humidity.get(),
+            pressure: self_.pressure.get(),
+            corrections: self_.corrections.get(),
+        }
     }
 
     // kind of defeats the purpose of encapsulation but I don't want to clone strings
@@ -206,6 +347,8 @@ impl SensorObj {
         }
         match sensor_values.values {
             Some(values) => {
+                // BUG: FIXME: fuckkkkkkkkkkkkkkk
+                let time = Timestamp::now().as_u32();
                 self.set_properties(&[
                     ("connected", &true),
                     ("temperature", &values.temperature),
@@ -213,13 +356,14 @@ impl SensorObj {
                     ("pressure", &values.pressure),
                 ])
                 .unwrap();
+                self_.last_update.set(time);
+                self.notify("last-update");
 
                 let live_timeseries = self_.live_timeseries.borrow_mut();
                 if let Some(timeseries) = &*live_timeseries.0 {
                     let mut timeseries = timeseries.borrow_mut();
                     let res = timeseries.push_row(TimeseriesRow {
-                        // BUG: FIXME: fuckkkkkkkkkkkkkkk
-                        time: Timestamp::now().as_u32(),
+                        time,
                         temperature: values.temperature as i16,
                         humidity: values.humidity,
                         pressure: values.pressure,
@@ -228,6 +372,7 @@ impl SensorObj {
                     drop(live_timeseries);
                     if res.is_ok() {
                         self.notify("live-timeseries");
+                        self.update_tendency();
                     }
                 }
             }
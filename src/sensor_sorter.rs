@@ -0,0 +1,165 @@
+use glib::subclass::prelude::*;
+use gtk::prelude::*;
+use std::cmp::Ordering;
+
+use crate::{sensor_obj::SensorObj, units::Unit};
+
+/// What the overview is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Label,
+    Temperature,
+    Humidity,
+    Pressure,
+    Connection,
+    LastUpdate,
+}
+
+impl Default for SortKey {
+    fn default() -> Self {
+        SortKey::Label
+    }
+}
+
+impl SortKey {
+    /// In the order of the overview dropdown.
+    pub const ALL: [SortKey; 6] = [
+        SortKey::Label,
+        SortKey::Temperature,
+        SortKey::Humidity,
+        SortKey::Pressure,
+        SortKey::Connection,
+        SortKey::LastUpdate,
+    ];
+
+    /// Name used in the settings.
+    pub fn nick(self) -> &'static str {
+        match self {
+            SortKey::Label => "label",
+            SortKey::Temperature => "temperature",
+            SortKey::Humidity => "humidity",
+            SortKey::Pressure => "pressure",
+            SortKey::Connection => "connection",
+            SortKey::LastUpdate => "last-update",
+        }
+    }
+
+    pub fn from_nick(nick: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|key| key.nick() == nick)
+    }
+
+    /// Properties of a sensor the order depends on.
+    pub fn properties(self) -> &'static [&'static str] {
+        match self {
+            SortKey::Label => &["label"],
+            SortKey::Connection => &["connected"],
+            SortKey::LastUpdate => &["last-update"],
+            // the values are all set before the last update
+            _ => &["connected", "last-update", "corrections"],
+        }
+    }
+
+    fn compare(self, a: &SensorObj, b: &SensorObj) -> Ordering {
+        let value = |unit: Unit| {
+            // disconnected sensors have no current values and come first
+            let values = |sensor: &SensorObj| {
+                Some(sensor.values().get(unit)).filter(|_| sensor.data().connected.get())
+            };
+            values(a).partial_cmp(&values(b)).unwrap_or(Ordering::Equal)
+        };
+        match self {
+            SortKey::Label => label(a).cmp(&label(b)),
+            SortKey::Temperature => value(Unit::Temperature),
+            SortKey::Humidity => value(Unit::Humidity),
+            SortKey::Pressure => value(Unit::Pressure),
+            SortKey::Connection => b.data().connected.get().cmp(&a.data().connected.get()),
+            SortKey::LastUpdate => a.data().last_update.get().cmp(&b.data().last_update.get()),
+        }
+    }
+}
+
+/// Label to sort by, sensors without a label go by their id.
+fn label(sensor: &SensorObj) -> String {
+    match &*sensor.data().label.borrow() {
+        Some(label) => label.to_lowercase(),
+        None => sensor.id().to_string(),
+    }
+}
+
+mod imp {
+    use gtk::subclass::prelude::SorterImpl;
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[derive(Default)]
+    pub struct SensorSorter {
+        pub key: Cell<SortKey>,
+        pub descending: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SensorSorter {
+        const NAME: &'static str = "BleWsSensorSorter";
+        type Type = super::SensorSorter;
+        type ParentType = gtk::Sorter;
+        type Interfaces = ();
+    }
+
+    impl ObjectImpl for SensorSorter {}
+
+    impl SorterImpl for SensorSorter {
+        fn compare(
+            &self,
+            _sorter: &Self::Type,
+            item1: &glib::Object,
+            item2: &glib::Object,
+        ) -> gtk::Ordering {
+            let a = item1.downcast_ref::<SensorObj>().unwrap();
+            let b = item2.downcast_ref::<SensorObj>().unwrap();
+            let mut ordering = self.key.get().compare(a, b);
+            if self.descending.get() {
+                ordering = ordering.reverse();
+            }
+            // favorites stay on top in either direction
+            let ordering = b.is_favorite().cmp(&a.is_favorite()).then(ordering);
+            // sensors with equal keys keep a fixed order
+            ordering
+                .then_with(|| label(a).cmp(&label(b)))
+                .then_with(|| a.id().cmp(&b.id()))
+                .into()
+        }
+
+        fn order(&self, _sorter: &Self::Type) -> gtk::SorterOrder {
+            gtk::SorterOrder::Total
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct SensorSorter(ObjectSubclass<imp::SensorSorter>) @extends gtk::Sorter;
+}
+
+impl SensorSorter {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Can't create SensorSorter")
+    }
+
+    pub fn key(&self) -> SortKey {
+        let self_ = imp::SensorSorter::from_instance(self);
+        self_.key.get()
+    }
+
+    pub fn set_sort(&self, key: SortKey, descending: bool) {
+        let self_ = imp::SensorSorter::from_instance(self);
+        let changed = self_.key.replace(key) != key;
+        if self_.descending.replace(descending) != descending || changed {
+            self.changed(gtk::SorterChange::Different);
+        }
+    }
+
+    /// Sort again after a value the order depends on changed.
+    pub fn resort(&self) {
+        self.changed(gtk::SorterChange::Different);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     data::{Timeseries, TimeseriesBuilder},
     event_loop::{Event, Label},
+    forecast::TENDENCY_HISTORY_SECONDS,
 };
 use ble_ws_api::{
     data::Timestamp,
@@ -29,8 +30,20 @@ pub struct Central {
 #[derive(Debug)]
 enum Command {
     FetchTimeseries(TimeseriesRequest),
-    SetEndpoint { url: Url, token: Token },
-    SetLabel { label: Label, id: Uuid },
+    /// Range that doesn't get displayed but answered on `reply`
+    FetchRange {
+        id: Uuid,
+        range: std::ops::RangeInclusive<Timestamp>,
+        reply: tokio::sync::oneshot::Sender<Result<Timeseries, anyhow::Error>>,
+    },
+    SetEndpoint {
+        url: Url,
+        token: Token,
+    },
+    SetLabel {
+        label: Label,
+        id: Uuid,
+    },
 }
 
 impl super::Service for Central {
@@ -112,7 +125,18 @@ async fn handle_cmd(
                             end: u32::MAX,
                         },
                     ),
-                    TimeseriesRequest::Range { id, range } => (
+                    TimeseriesRequest::Tendency(id) => (
+                        id,
+                        ble_ws_api::proto::SensorDataRequest {
+                            id: Some(proto::Uuid::from(*id)),
+                            start: Timestamp::now()
+                                .bottoming_sub(Timestamp::from(TENDENCY_HISTORY_SECONDS))
+                                .as_u32(),
+                            end: u32::MAX,
+                        },
+                    ),
+                    TimeseriesRequest::Range { id, range }
+                    | TimeseriesRequest::ShiftedRange { id, range, .. } => (
                         id,
                         ble_ws_api::proto::SensorDataRequest {
                             id: Some(proto::Uuid::from(*id)),
@@ -121,39 +145,45 @@ async fn handle_cmd(
                         },
                     ),
                 };
-                let resp = client_handle
-                    .client
-                    .get_sensor_data(req)
-                    .await?
-                    .into_inner();
-                let timeseries = TimeseriesBuilder::default()
-                    .time(resp.time)
-                    .temperature(resp.temperature.into_iter().map(|n| n as i16).collect())
-                    .humidity(resp.humidity)
-                    .pressure(resp.pressure)
-                    .build();
-                match timeseries {
-                    Ok(timeseries) => {
-                        sender
-                            .send(Event::FetchedTimeseries {
-                                id,
-                                timeseries: match kind {
-                                    TimeseriesRequest::Live(_) => {
-                                        TimeseriesResponse::Live(timeseries)
-                                    }
-                                    TimeseriesRequest::Range { .. } => {
-                                        TimeseriesResponse::Range(timeseries)
-                                    }
-                                },
-                            })
-                            .unwrap();
-                    }
-                    Err(_) => {
-                        tracing::error!("Received invalid length timeseries from endpoint");
-                    }
-                }
+                let timeseries = get_sensor_data(&mut client_handle.client, req).await?;
+                sender
+                    .send(Event::FetchedTimeseries {
+                        id,
+                        timeseries: match kind {
+                            TimeseriesRequest::Live(_) => TimeseriesResponse::Live(timeseries),
+                            TimeseriesRequest::Tendency(_) => {
+                                TimeseriesResponse::Tendency(timeseries)
+                            }
+                            TimeseriesRequest::Range { .. } => {
+                                TimeseriesResponse::Range(timeseries)
+                            }
+                            TimeseriesRequest::ShiftedRange { shift, .. } => {
+                                TimeseriesResponse::ShiftedRange { timeseries, shift }
+                            }
+                        },
+                    })
+                    .unwrap();
             }
         }
+
+        Command::FetchRange { id, range, reply } => {
+            let timeseries = match client_handle {
+                Some(client_handle) => {
+                    get_sensor_data(
+                        &mut client_handle.client,
+                        ble_ws_api::proto::SensorDataRequest {
+                            id: Some(proto::Uuid::from(id)),
+                            start: range.start().as_u32(),
+                            end: range.end().as_u32(),
+                        },
+                    )
+                    .await
+                }
+                None => Err(anyhow::format_err!("Not connected to an endpoint")),
+            };
+            // the requester may have given up already
+            let _ = reply.send(timeseries);
+        }
         Command::SetEndpoint { url, token } => {
             // TODO: make name less obnoxious
             let channel = tonic::transport::Channel::from_shared(url.to_string())?
@@ -199,6 +229,20 @@ async fn handle_cmd(
     Ok(())
 }
 
+async fn get_sensor_data(
+    client: &mut BleWeatherstationServiceClient<InterceptedService<Channel, AuthInterceptor>>,
+    req: ble_ws_api::proto::SensorDataRequest,
+) -> Result<Timeseries, anyhow::Error> {
+    let resp = client.get_sensor_data(req).await?.into_inner();
+    TimeseriesBuilder::default()
+        .time(resp.time)
+        .temperature(resp.temperature.into_iter().map(|n| n as i16).collect())
+        .humidity(resp.humidity)
+        .pressure(resp.pressure)
+        .build()
+        .map_err(|_| anyhow::format_err!("Received invalid length timeseries from endpoint"))
+}
+
 fn overview_transform(resp: OverviewResponse) -> Vec<(Uuid, SensorOverview)> {
     resp.overview
         .into_iter()
@@ -209,15 +253,26 @@ fn overview_transform(resp: OverviewResponse) -> Vec<(Uuid, SensorOverview)> {
 #[derive(Clone, Debug)]
 pub enum TimeseriesRequest {
     Live(Uuid),
+    /// Only the recent history needed for the pressure tendency
+    Tendency(Uuid),
     Range {
         id: Uuid,
         range: std::ops::RangeInclusive<Timestamp>,
     },
+    /// Range of an earlier period that gets drawn `shift` seconds later
+    /// on top of the current one
+    ShiftedRange {
+        id: Uuid,
+        range: std::ops::RangeInclusive<Timestamp>,
+        shift: u32,
+    },
 }
 
 pub enum TimeseriesResponse {
     Live(Timeseries),
+    Tendency(Timeseries),
     Range(Timeseries),
+    ShiftedRange { timeseries: Timeseries, shift: u32 },
 }
 
 impl Central {
@@ -229,6 +284,17 @@ impl Central {
         self.send(Command::FetchTimeseries(req));
     }
 
+    /// Fetch a range without going through the event loop, blocks until it arrived.
+    pub fn fetch_range_blocking(
+        &self,
+        id: Uuid,
+        range: std::ops::RangeInclusive<Timestamp>,
+    ) -> Result<Timeseries, anyhow::Error> {
+        let (reply, rx) = tokio::sync::oneshot::channel();
+        self.send(Command::FetchRange { id, range, reply });
+        rx.blocking_recv()?
+    }
+
     pub fn set_endpoint(&self, url: url::Url, token: Token) {
         tracing::info!("Connecting to endpoint {}", url);
         self.send(Command::SetEndpoint { url, token });
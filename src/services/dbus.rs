@@ -0,0 +1,344 @@
+//! Session bus interface for tools that want current readings without gRPC.
+//!
+//! GApplication already owns the application id on the bus, so the
+//! interface lives under its own well known name.
+
+use super::search_provider::{self, SearchProvider, ShowSensor};
+use crate::{
+    data::{Corrections, Timeseries},
+    sensor_obj::SensorObj,
+    units::Unit,
+};
+use ble_ws_api::data::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+use zbus::dbus_interface;
+use zvariant::derive::Type;
+
+pub const BUS_NAME: &str = "li._5kw.BleWsGtk.Sensors";
+pub const OBJECT_PATH: &str = "/li/_5kw/BleWsGtk/Sensors";
+pub const INTERFACE: &str = "li._5kw.BleWsGtk.Sensors";
+
+/// Latest calibrated readings of a sensor in raw sensor units and where it
+/// is, `(ssbiuusas)` on the bus.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Type)]
+pub struct SensorInfo {
+    pub id: String,
+    pub label: String,
+    pub connected: bool,
+    pub temperature: i32,
+    pub humidity: u32,
+    pub pressure: u32,
+    pub room: String,
+    pub tags: Vec<String>,
+}
+
+impl From<&SensorObj> for SensorInfo {
+    fn from(sensor: &SensorObj) -> Self {
+        let data = sensor.data();
+        // calibrated like everywhere else in the app
+        let values = sensor.values();
+        let metadata = sensor.settings().metadata;
+        Self {
+            id: sensor.id().to_string(),
+            label: data.label.borrow().clone().unwrap_or_default(),
+            connected: data.connected.get(),
+            temperature: values.get(Unit::Temperature).round() as i32,
+            humidity: values.get(Unit::Humidity).round() as u32,
+            pressure: values.get(Unit::Pressure).round() as u32,
+            room: metadata.room,
+            tags: metadata.tags,
+        }
+    }
+}
+
+/// Columns of a timeseries, `(auanauau)` on the bus.
+pub type RangeColumns = (Vec<u32>, Vec<i16>, Vec<u32>, Vec<u32>);
+
+pub type FetchRange =
+    Box<dyn Fn(Uuid, RangeInclusive<Timestamp>) -> Result<Timeseries, anyhow::Error> + Send>;
+
+/// What the bus interfaces can ask of the rest of the app.
+pub struct Handlers {
+    pub fetch_range: FetchRange,
+    pub show_sensor: ShowSensor,
+}
+
+/// Corrections of the exported sensors by their id.
+type SensorCorrections = Arc<Mutex<HashMap<String, Corrections>>>;
+
+struct Sensors {
+    sensors: Arc<Mutex<Vec<SensorInfo>>>,
+    corrections: SensorCorrections,
+    fetch_range: FetchRange,
+}
+
+#[dbus_interface(name = "li._5kw.BleWsGtk.Sensors")]
+impl Sensors {
+    /// All known sensors with their latest readings.
+    fn list(&self) -> Vec<SensorInfo> {
+        self.sensors.lock().unwrap().clone()
+    }
+
+    /// Readings of sensor `id` between the unix timestamps `from` and `to`,
+    /// corrected like the ones in `List`.
+    fn range(&self, id: &str, from: u32, to: u32) -> zbus::fdo::Result<RangeColumns> {
+        let id = Uuid::parse_str(id)
+            .map_err(|e| zbus::fdo::Error::InvalidArgs(format!("Invalid sensor id: {}", e)))?;
+        if from > to {
+            return Err(zbus::fdo::Error::InvalidArgs("from is after to".to_owned()));
+        }
+        let mut timeseries = (self.fetch_range)(id, Timestamp::from(from)..=Timestamp::from(to))
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+        let corrections = self
+            .corrections
+            .lock()
+            .unwrap()
+            .get(&id.to_string())
+            .copied()
+            .unwrap_or_default();
+        timeseries.set_corrections(corrections);
+        let column = |unit: Unit| timeseries.column(unit).into_iter().map(f64::round);
+        Ok((
+            timeseries.time().to_vec(),
+            column(Unit::Temperature).map(|v| v as i16).collect(),
+            column(Unit::Humidity).map(|v| v as u32).collect(),
+            column(Unit::Pressure).map(|v| v as u32).collect(),
+        ))
+    }
+
+    /// Emitted after every update of the readings.
+    #[dbus_interface(signal)]
+    fn sensors_changed(&self) -> zbus::Result<()>;
+}
+
+/// Exports the sensors on a bus from a thread of its own.
+pub struct DbusServer {
+    connection: zbus::Connection,
+    sensors: Arc<Mutex<Vec<SensorInfo>>>,
+    corrections: SensorCorrections,
+}
+
+impl DbusServer {
+    pub fn session(handlers: Handlers) -> Result<Self, anyhow::Error> {
+        Self::start(zbus::Connection::new_session()?, handlers)
+    }
+
+    /// Serve on the bus at `address`, used to test against a private bus.
+    pub fn for_address(address: &str, handlers: Handlers) -> Result<Self, anyhow::Error> {
+        Self::start(zbus::Connection::new_for_address(address, true)?, handlers)
+    }
+
+    fn start(connection: zbus::Connection, handlers: Handlers) -> Result<Self, anyhow::Error> {
+        let sensors = Arc::new(Mutex::new(Vec::new()));
+        let corrections = SensorCorrections::default();
+
+        // the object server can't be moved between threads, so create it in the one serving it
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        std::thread::spawn({
+            let connection = connection.clone();
+            let sensors = sensors.clone();
+            let corrections = corrections.clone();
+            move || {
+                let mut object_server = zbus::ObjectServer::new(&connection);
+                let registered = (|| -> Result<(), anyhow::Error> {
+                    object_server.at(
+                        &zvariant::ObjectPath::try_from(OBJECT_PATH)?,
+                        Sensors {
+                            sensors: sensors.clone(),
+                            corrections,
+                            fetch_range: handlers.fetch_range,
+                        },
+                    )?;
+                    object_server.at(
+                        &zvariant::ObjectPath::try_from(search_provider::OBJECT_PATH)?,
+                        SearchProvider {
+                            sensors,
+                            show_sensor: handlers.show_sensor,
+                        },
+                    )?;
+                    Ok(())
+                })();
+                let failed = registered.is_err();
+                let _ = ready_tx.send(registered);
+                if failed {
+                    return;
+                }
+                loop {
+                    if let Err(e) = object_server.try_handle_next() {
+                        tracing::error!("Failed handling D-Bus message: {}", e);
+                    }
+                }
+            }
+        });
+        ready_rx.recv()??;
+
+        zbus::fdo::DBusProxy::new(&connection)?
+            .request_name(BUS_NAME, zbus::fdo::RequestNameFlags::DoNotQueue.into())?;
+
+        Ok(Self {
+            connection,
+            sensors,
+            corrections,
+        })
+    }
+
+    /// Replace the exported sensors and the corrections of their ranges, then
+    /// emit `SensorsChanged`.
+    pub fn set_sensors(&self, sensors: Vec<(SensorInfo, Corrections)>) {
+        *self.corrections.lock().unwrap() = sensors
+            .iter()
+            .map(|(info, corrections)| (info.id.clone(), *corrections))
+            .collect();
+        *self.sensors.lock().unwrap() = sensors.into_iter().map(|(info, _)| info).collect();
+        if let Err(e) =
+            self.connection
+                .emit_signal(None, OBJECT_PATH, INTERFACE, "SensorsChanged", &())
+        {
+            tracing::error!("Failed emitting SensorsChanged: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::TimeseriesBuilder,
+        local_store::{Calibration, Calibrations},
+    };
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+
+    /// A dbus-daemon only reachable by the test.
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        fn start() -> Self {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(&["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("Could not start dbus-daemon");
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.as_mut().unwrap())
+                .read_line(&mut address)
+                .expect("Could not read the bus address");
+            Self {
+                daemon,
+                address: address.trim().to_owned(),
+            }
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn sensor(id: Uuid) -> SensorInfo {
+        SensorInfo {
+            id: id.to_string(),
+            label: "Basement".to_owned(),
+            connected: true,
+            temperature: 2150,
+            humidity: 6320,
+            pressure: 1013250,
+            room: "Cellar".to_owned(),
+            tags: vec!["indoor".to_owned()],
+        }
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon, run with --ignored"]
+    fn sensors_and_search() {
+        let bus = PrivateBus::start();
+
+        let id = Uuid::from_u128(0x6c2f_3f1e_8a4b_4c1d_9e7f_2a5b_0c3d_1e4f);
+        let (shown_tx, shown_rx) = std::sync::mpsc::channel();
+        let server = DbusServer::for_address(
+            &bus.address,
+            Handlers {
+                fetch_range: Box::new(|_, range| {
+                    TimeseriesBuilder::default()
+                        .time(vec![range.start().as_u32(), range.end().as_u32()])
+                        .temperature(vec![2000, 2100])
+                        .humidity(vec![5000, 5100])
+                        .pressure(vec![1000000, 1000100])
+                        .build()
+                }),
+                show_sensor: Box::new(move |id| shown_tx.send(id).unwrap()),
+            },
+        )
+        .unwrap();
+        let corrections = Corrections {
+            calibration: Some(Calibrations {
+                temperature: Calibration {
+                    offset: -50.,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        server.set_sensors(vec![(sensor(id), corrections)]);
+
+        let client = zbus::Connection::new_for_address(&bus.address, true).unwrap();
+        let proxy = zbus::Proxy::new(&client, BUS_NAME, OBJECT_PATH, INTERFACE).unwrap();
+
+        let sensors: Vec<SensorInfo> = proxy.call("List", &()).unwrap();
+        assert_eq!(sensors, vec![sensor(id)]);
+
+        let (time, temperature, humidity, pressure): RangeColumns = proxy
+            .call("Range", &(id.to_string(), 100u32, 200u32))
+            .unwrap();
+        assert_eq!(time, vec![100, 200]);
+        // calibrated like the sensor's values
+        assert_eq!(temperature, vec![1950, 2050]);
+        assert_eq!(humidity, vec![5000, 5100]);
+        assert_eq!(pressure, vec![1000000, 1000100]);
+
+        assert!(proxy
+            .call::<_, RangeColumns>("Range", &(id.to_string(), 200u32, 100u32))
+            .is_err());
+
+        let search = zbus::Proxy::new(
+            &client,
+            BUS_NAME,
+            search_provider::OBJECT_PATH,
+            "org.gnome.Shell.SearchProvider2",
+        )
+        .unwrap();
+        let found: Vec<String> = search
+            .call("GetInitialResultSet", &(vec!["Base"],))
+            .unwrap();
+        assert_eq!(found, vec![id.to_string()]);
+        let found: Vec<String> = search
+            .call("GetSubsearchResultSet", &(found, vec!["Kitchen"]))
+            .unwrap();
+        assert!(found.is_empty());
+        // like in the app the room and tags can be searched for
+        for term in ["cellar", "indoor"] {
+            let found: Vec<String> = search.call("GetInitialResultSet", &(vec![term],)).unwrap();
+            assert_eq!(found, vec![id.to_string()], "{}", term);
+        }
+
+        search
+            .call::<_, ()>("ActivateResult", &(id.to_string(), vec!["Base"], 0u32))
+            .unwrap();
+        assert_eq!(shown_rx.recv().unwrap(), id);
+    }
+}
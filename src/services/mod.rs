@@ -1,4 +1,6 @@
 pub mod central;
+pub mod dbus;
+pub mod search_provider;
 
 use crate::event_loop::Event;
 use tokio::{runtime::Handle, sync::oneshot};
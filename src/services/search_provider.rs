@@ -0,0 +1,126 @@
+//! `org.gnome.Shell.SearchProvider2` so sensors show up in the GNOME overview.
+
+use super::dbus::SensorInfo;
+use crate::{
+    search::{Query, Searchable},
+    units::{Unit, UnitSystem},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+use zbus::dbus_interface;
+use zvariant::{OwnedValue, Value};
+
+pub const OBJECT_PATH: &str = "/li/_5kw/BleWsGtk/SearchProvider";
+
+pub type ShowSensor = Box<dyn Fn(Uuid) + Send>;
+
+pub struct SearchProvider {
+    pub sensors: Arc<Mutex<Vec<SensorInfo>>>,
+    pub show_sensor: ShowSensor,
+}
+
+impl SearchProvider {
+    fn matching(&self, candidates: Option<&[String]>, terms: &[String]) -> Vec<String> {
+        let query = Query::parse(&terms.join(" "));
+        self.sensors
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|sensor| candidates.map_or(true, |candidates| candidates.contains(&sensor.id)))
+            // an empty label would match everything and the shell has nothing to show for it
+            .filter(|sensor| !sensor.label.is_empty())
+            .filter(|sensor| {
+                let searchable = Searchable {
+                    label: Some(&sensor.label),
+                    id: &sensor.id,
+                    room: &sensor.room,
+                    tags: &sensor.tags,
+                    connected: sensor.connected,
+                };
+                query.matches(&searchable, |unit| value(sensor, unit))
+            })
+            .map(|sensor| sensor.id.clone())
+            .collect()
+    }
+}
+
+/// Current value of `unit` in the displayed units, the derived ones aren't
+/// on the bus.
+fn value(sensor: &SensorInfo, unit: Unit) -> f64 {
+    let raw = match unit {
+        Unit::Temperature => f64::from(sensor.temperature),
+        Unit::Humidity => f64::from(sensor.humidity),
+        Unit::Pressure => f64::from(sensor.pressure),
+        _ => return f64::NAN,
+    };
+    UnitSystem::current().convert(unit, raw)
+}
+
+fn description(sensor: &SensorInfo) -> String {
+    if !sensor.connected {
+        return "Disconnected".to_owned();
+    }
+    format!(
+        "{} · {}",
+        Unit::Temperature.format_value(f64::from(sensor.temperature)),
+        Unit::Humidity.format_value(f64::from(sensor.humidity))
+    )
+}
+
+#[dbus_interface(name = "org.gnome.Shell.SearchProvider2")]
+impl SearchProvider {
+    fn get_initial_result_set(&self, terms: Vec<String>) -> Vec<String> {
+        self.matching(None, &terms)
+    }
+
+    fn get_subsearch_result_set(
+        &self,
+        previous_results: Vec<String>,
+        terms: Vec<String>,
+    ) -> Vec<String> {
+        self.matching(Some(&previous_results), &terms)
+    }
+
+    fn get_result_metas(&self, identifiers: Vec<String>) -> Vec<HashMap<String, OwnedValue>> {
+        let sensors = self.sensors.lock().unwrap();
+        identifiers
+            .iter()
+            .filter_map(|id| sensors.iter().find(|sensor| sensor.id == *id))
+            .map(|sensor| {
+                let mut meta = HashMap::new();
+                meta.insert("id".to_owned(), Value::from(sensor.id.as_str()).into());
+                meta.insert("name".to_owned(), Value::from(sensor.label.as_str()).into());
+                meta.insert(
+                    "description".to_owned(),
+                    Value::from(description(sensor)).into(),
+                );
+                meta.insert(
+                    "gicon".to_owned(),
+                    Value::from(crate::config::APP_ID).into(),
+                );
+                meta
+            })
+            .collect()
+    }
+
+    fn activate_result(&self, identifier: &str, _terms: Vec<String>, _timestamp: u32) {
+        match Uuid::parse_str(identifier) {
+            Ok(id) => (self.show_sensor)(id),
+            Err(e) => tracing::error!("Shell activated invalid sensor {}: {}", identifier, e),
+        }
+    }
+
+    fn launch_search(&self, terms: Vec<String>, _timestamp: u32) {
+        // there is no search to continue in, show the first match instead
+        if let Some(id) = self
+            .matching(None, &terms)
+            .first()
+            .and_then(|id| Uuid::parse_str(id).ok())
+        {
+            (self.show_sensor)(id);
+        }
+    }
+}
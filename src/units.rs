@@ -0,0 +1,472 @@
+//! The quantities the sensors measure or get derived from them, the units
+//! values are displayed in and the one place raw sensor values get converted
+//! and formatted for display, with the decimal separator of the locale.
+
+use gio::prelude::*;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, glib::GEnum, Serialize, Deserialize)]
+#[repr(u8)]
+#[genum(type_name = "BleWsGraphUnit")]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Temperature = 0,
+    Humidity = 1,
+    Pressure = 2,
+    // computed from temperature and humidity, see `crate::physics`
+    DewPoint = 3,
+    AbsoluteHumidity = 4,
+    HeatIndex = 5,
+    VapourPressureDeficit = 6,
+}
+
+impl Default for Unit {
+    fn default() -> Self {
+        Self::Temperature
+    }
+}
+
+impl std::str::FromStr for Unit {
+    type Err = ();
+
+    /// Parses the lowercase names also used for the detail pages.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Unit::ALL
+            .iter()
+            .copied()
+            .find(|unit| unit.name() == s)
+            .ok_or(())
+    }
+}
+
+impl Unit {
+    /// Every unit, in the order of the detail pages.
+    pub const ALL: [Unit; 7] = [
+        Unit::Temperature,
+        Unit::Humidity,
+        Unit::Pressure,
+        Unit::DewPoint,
+        Unit::AbsoluteHumidity,
+        Unit::HeatIndex,
+        Unit::VapourPressureDeficit,
+    ];
+
+    /// The units the sensors measure.
+    pub const MEASURED: [Unit; 3] = [Unit::Temperature, Unit::Humidity, Unit::Pressure];
+
+    pub fn as_usize(self) -> usize {
+        usize::from(self as u8)
+    }
+
+    /// Lowercase name used in links, settings and as detail page name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Unit::Temperature => "temperature",
+            Unit::Humidity => "humidity",
+            Unit::Pressure => "pressure",
+            Unit::DewPoint => "dewpoint",
+            Unit::AbsoluteHumidity => "absolutehumidity",
+            Unit::HeatIndex => "heatindex",
+            Unit::VapourPressureDeficit => "vapourpressuredeficit",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Unit::Temperature => "Temperature",
+            Unit::Humidity => "Humidity",
+            Unit::Pressure => "Pressure",
+            Unit::DewPoint => "Dew point",
+            Unit::AbsoluteHumidity => "Absolute humidity",
+            Unit::HeatIndex => "Heat index",
+            Unit::VapourPressureDeficit => "Vapour pressure deficit",
+        }
+    }
+
+    /// Raw values per °C, %, hPa, g/m³ or kPa, display units are converted from those.
+    pub fn scale(self) -> f64 {
+        match self {
+            Unit::Temperature | Unit::DewPoint | Unit::HeatIndex => 100.,
+            Unit::Humidity | Unit::AbsoluteHumidity => 100.,
+            Unit::Pressure | Unit::VapourPressureDeficit => 1000.,
+        }
+    }
+
+    /// Format a difference between two raw values of this unit.
+    pub fn format_delta(self, delta: f64) -> String {
+        UnitSystem::current().format_delta(self, delta)
+    }
+
+    /// Format a raw value of this unit in the units picked in the preferences.
+    pub fn format_value(self, value: f64) -> String {
+        UnitSystem::current().format_value(self, value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressureUnit {
+    Hectopascal,
+    Kilopascal,
+    InchOfMercury,
+    MillimeterOfMercury,
+}
+
+impl TemperatureUnit {
+    /// In the order of the preferences dropdown.
+    pub const ALL: [TemperatureUnit; 3] = [
+        TemperatureUnit::Celsius,
+        TemperatureUnit::Fahrenheit,
+        TemperatureUnit::Kelvin,
+    ];
+
+    /// Name used in the settings.
+    pub fn nick(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "celsius",
+            TemperatureUnit::Fahrenheit => "fahrenheit",
+            TemperatureUnit::Kelvin => "kelvin",
+        }
+    }
+
+    pub fn from_nick(nick: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|unit| unit.nick() == nick)
+    }
+}
+
+impl PressureUnit {
+    /// In the order of the preferences dropdown.
+    pub const ALL: [PressureUnit; 4] = [
+        PressureUnit::Hectopascal,
+        PressureUnit::Kilopascal,
+        PressureUnit::InchOfMercury,
+        PressureUnit::MillimeterOfMercury,
+    ];
+
+    /// Name used in the settings.
+    pub fn nick(self) -> &'static str {
+        match self {
+            PressureUnit::Hectopascal => "hpa",
+            PressureUnit::Kilopascal => "kpa",
+            PressureUnit::InchOfMercury => "inhg",
+            PressureUnit::MillimeterOfMercury => "mmhg",
+        }
+    }
+
+    pub fn from_nick(nick: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|unit| unit.nick() == nick)
+    }
+
+    /// Displayed value per hPa.
+    fn per_hectopascal(self) -> f64 {
+        match self {
+            PressureUnit::Hectopascal => 1.,
+            PressureUnit::Kilopascal => 0.1,
+            PressureUnit::InchOfMercury => 0.029_529_983_071_445,
+            PressureUnit::MillimeterOfMercury => 0.750_061_682_704_17,
+        }
+    }
+}
+
+/// The units chosen in the preferences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnitSystem {
+    pub temperature: TemperatureUnit,
+    pub pressure: PressureUnit,
+}
+
+impl Default for UnitSystem {
+    fn default() -> Self {
+        Self {
+            temperature: TemperatureUnit::Celsius,
+            pressure: PressureUnit::Hectopascal,
+        }
+    }
+}
+
+// shared with the D-Bus thread, which formats values for the shell search
+static CURRENT: Lazy<RwLock<UnitSystem>> = Lazy::new(Default::default);
+
+impl UnitSystem {
+    pub fn from_settings(settings: &gio::Settings) -> Self {
+        let default = Self::default();
+        Self {
+            temperature: TemperatureUnit::from_nick(&settings.string("temperature-unit"))
+                .unwrap_or(default.temperature),
+            pressure: PressureUnit::from_nick(&settings.string("pressure-unit"))
+                .unwrap_or(default.pressure),
+        }
+    }
+
+    /// The units everything is currently displayed in.
+    pub fn current() -> Self {
+        *CURRENT.read().unwrap()
+    }
+
+    pub fn set_current(self) {
+        *CURRENT.write().unwrap() = self;
+    }
+
+    pub fn symbol(self, unit: Unit) -> &'static str {
+        match unit {
+            Unit::Temperature | Unit::DewPoint | Unit::HeatIndex => match self.temperature {
+                TemperatureUnit::Celsius => "°C",
+                TemperatureUnit::Fahrenheit => "°F",
+                TemperatureUnit::Kelvin => "K",
+            },
+            Unit::Humidity => "%",
+            Unit::Pressure => match self.pressure {
+                PressureUnit::Hectopascal => "hPa",
+                PressureUnit::Kilopascal => "kPa",
+                PressureUnit::InchOfMercury => "inHg",
+                PressureUnit::MillimeterOfMercury => "mmHg",
+            },
+            Unit::AbsoluteHumidity => "g/m³",
+            // small enough that kPa is what everyone uses
+            Unit::VapourPressureDeficit => "kPa",
+        }
+    }
+
+    /// Decimal places worth showing for `unit`.
+    pub fn precision(self, unit: Unit) -> usize {
+        match unit {
+            Unit::Pressure => match self.pressure {
+                PressureUnit::Hectopascal | PressureUnit::MillimeterOfMercury => 1,
+                PressureUnit::Kilopascal => 2,
+                PressureUnit::InchOfMercury => 3,
+            },
+            _ => 2,
+        }
+    }
+
+    /// Convert a difference of raw values, offsets between units don't apply.
+    pub fn convert_delta(self, unit: Unit, raw: f64) -> f64 {
+        let value = raw / unit.scale();
+        match unit {
+            Unit::Temperature | Unit::DewPoint | Unit::HeatIndex => match self.temperature {
+                TemperatureUnit::Fahrenheit => value * 9. / 5.,
+                _ => value,
+            },
+            Unit::Pressure => value * self.pressure.per_hectopascal(),
+            _ => value,
+        }
+    }
+
+    /// Convert a raw sensor value to the displayed unit.
+    pub fn convert(self, unit: Unit, raw: f64) -> f64 {
+        self.convert_delta(unit, raw) + self.offset(unit)
+    }
+
+    /// Inverse of [`Self::convert_delta`].
+    pub fn to_raw_delta(self, unit: Unit, value: f64) -> f64 {
+        let value = match unit {
+            Unit::Temperature | Unit::DewPoint | Unit::HeatIndex => match self.temperature {
+                TemperatureUnit::Fahrenheit => value * 5. / 9.,
+                _ => value,
+            },
+            Unit::Pressure => value / self.pressure.per_hectopascal(),
+            _ => value,
+        };
+        value * unit.scale()
+    }
+
+    /// Inverse of [`Self::convert`], for values entered by the user.
+    pub fn to_raw(self, unit: Unit, value: f64) -> f64 {
+        self.to_raw_delta(unit, value - self.offset(unit))
+    }
+
+    /// Displayed value of zero in the base unit.
+    fn offset(self, unit: Unit) -> f64 {
+        match unit {
+            Unit::Temperature | Unit::DewPoint | Unit::HeatIndex => match self.temperature {
+                TemperatureUnit::Celsius => 0.,
+                TemperatureUnit::Fahrenheit => 32.,
+                TemperatureUnit::Kelvin => 273.15,
+            },
+            _ => 0.,
+        }
+    }
+
+    /// Format a raw sensor value, e.g. `21.50°C` or `29.921 inHg`.
+    pub fn format_value(self, unit: Unit, raw: f64) -> String {
+        self.format(unit, self.convert(unit, raw), decimal_separator())
+    }
+
+    pub fn format_delta(self, unit: Unit, raw: f64) -> String {
+        self.format(unit, self.convert_delta(unit, raw), decimal_separator())
+    }
+
+    fn format(self, unit: Unit, value: f64, decimal_separator: char) -> String {
+        if !value.is_finite() {
+            return "—".to_owned();
+        }
+        let precision = self.precision(unit);
+        // don't show -0.00 for values rounding to zero
+        let value = if (value * 10_f64.powi(precision as i32)).round() == 0. {
+            0.
+        } else {
+            value
+        };
+        let mut number = format!("{:.*}", precision, value);
+        if decimal_separator != '.' {
+            number = number.replace('.', &decimal_separator.to_string());
+        }
+
+        let symbol = self.symbol(unit);
+        // degrees and percent stick to the number
+        let separator = match symbol {
+            "°C" | "°F" | "%" => "",
+            _ => " ",
+        };
+        format!("{}{}{}", number, separator, symbol)
+    }
+}
+
+/// Decimal separator of the numeric locale GTK set up from the environment.
+fn decimal_separator() -> char {
+    static SEPARATOR: Lazy<char> = Lazy::new(|| {
+        // SAFETY: localeconv returns static data that only changes with
+        // setlocale, which isn't called again after GTK initialization
+        unsafe {
+            let conv = libc::localeconv();
+            if conv.is_null() || (*conv).decimal_point.is_null() {
+                return '.';
+            }
+            std::ffi::CStr::from_ptr((*conv).decimal_point)
+                .to_str()
+                .ok()
+                .and_then(|point| point.chars().next())
+                .unwrap_or('.')
+        }
+    });
+    *SEPARATOR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRIC: UnitSystem = UnitSystem {
+        temperature: TemperatureUnit::Celsius,
+        pressure: PressureUnit::Hectopascal,
+    };
+
+    fn system(temperature: TemperatureUnit, pressure: PressureUnit) -> UnitSystem {
+        UnitSystem {
+            temperature,
+            pressure,
+        }
+    }
+
+    #[test]
+    fn format_value() {
+        use PressureUnit::*;
+        use TemperatureUnit::*;
+        #[rustfmt::skip]
+        let cases = [
+            (METRIC, Unit::Temperature, 2150., "21.50°C"),
+            (METRIC, Unit::Temperature, -4000., "-40.00°C"),
+            (METRIC, Unit::Temperature, -0.1, "0.00°C"),
+            (METRIC, Unit::Temperature, f64::from(i16::MIN), "-327.68°C"),
+            (METRIC, Unit::Temperature, f64::from(i16::MAX), "327.67°C"),
+            (system(Fahrenheit, Hectopascal), Unit::Temperature, 2150., "70.70°F"),
+            (system(Fahrenheit, Hectopascal), Unit::Temperature, -4000., "-40.00°F"),
+            (system(Kelvin, Hectopascal), Unit::Temperature, 2150., "294.65 K"),
+            (METRIC, Unit::Humidity, 6320., "63.20%"),
+            (METRIC, Unit::Humidity, 0., "0.00%"),
+            (METRIC, Unit::Humidity, f64::from(u32::MAX), "42949672.95%"),
+            (METRIC, Unit::Pressure, 1005000., "1005.0 hPa"),
+            (METRIC, Unit::Pressure, 1013270., "1013.3 hPa"),
+            (METRIC, Unit::Pressure, 999., "1.0 hPa"),
+            (system(Celsius, Kilopascal), Unit::Pressure, 1005000., "100.50 kPa"),
+            (system(Celsius, InchOfMercury), Unit::Pressure, 1013250., "29.921 inHg"),
+            (system(Celsius, MillimeterOfMercury), Unit::Pressure, 1013250., "760.0 mmHg"),
+            (system(Fahrenheit, Hectopascal), Unit::DewPoint, 926., "48.67°F"),
+            (system(Kelvin, Hectopascal), Unit::HeatIndex, 4110., "314.25 K"),
+            (METRIC, Unit::AbsoluteHumidity, 862., "8.62 g/m³"),
+            (system(Celsius, InchOfMercury), Unit::VapourPressureDeficit, 1260., "1.26 kPa"),
+            (METRIC, Unit::Temperature, f64::NAN, "—"),
+            (METRIC, Unit::Pressure, f64::INFINITY, "—"),
+        ];
+        for (units, unit, raw, expected) in cases.iter() {
+            assert_eq!(
+                units.format(*unit, units.convert(*unit, *raw), '.'),
+                *expected,
+                "{:?} {:?} {}",
+                units,
+                unit,
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn format_delta() {
+        use PressureUnit::*;
+        use TemperatureUnit::*;
+        #[rustfmt::skip]
+        let cases = [
+            (METRIC, Unit::Temperature, 100., "1.00°C"),
+            (system(Fahrenheit, Hectopascal), Unit::Temperature, 100., "1.80°F"),
+            (system(Kelvin, Hectopascal), Unit::Temperature, -100., "-1.00 K"),
+            (METRIC, Unit::Humidity, 250., "2.50%"),
+            (system(Celsius, Kilopascal), Unit::Pressure, 2000., "0.20 kPa"),
+        ];
+        for (units, unit, raw, expected) in cases.iter() {
+            assert_eq!(
+                units.format(*unit, units.convert_delta(*unit, *raw), '.'),
+                *expected,
+                "{:?} {:?} {}",
+                units,
+                unit,
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn decimal_separator() {
+        let cases = [
+            (Unit::Temperature, 2150., "21,50°C"),
+            (Unit::Temperature, -4000., "-40,00°C"),
+            (Unit::Humidity, 6320., "63,20%"),
+            (Unit::Pressure, 1005000., "1005,0 hPa"),
+        ];
+        for (unit, raw, expected) in cases.iter() {
+            assert_eq!(
+                METRIC.format(*unit, METRIC.convert(*unit, *raw), ','),
+                *expected
+            );
+        }
+    }
+
+    #[test]
+    fn entered_values_round_trip() {
+        for temperature in TemperatureUnit::ALL.iter() {
+            for pressure in PressureUnit::ALL.iter() {
+                let units = system(*temperature, *pressure);
+                let cases = [
+                    (Unit::Temperature, -4000.),
+                    (Unit::Temperature, 2150.),
+                    (Unit::Humidity, 6320.),
+                    (Unit::Pressure, 1013250.),
+                    (Unit::DewPoint, 926.),
+                ];
+                for (unit, raw) in cases.iter() {
+                    let value = units.convert(*unit, *raw);
+                    assert!((units.to_raw(*unit, value) - raw).abs() < 1e-6);
+                    let delta = units.convert_delta(*unit, *raw);
+                    assert!((units.to_raw_delta(*unit, delta) - raw).abs() < 1e-6);
+                }
+            }
+        }
+    }
+}
@@ -5,3 +5,17 @@ pub fn now_local() -> time::OffsetDateTime {
         time::OffsetDateTime::now_local().unwrap()
     }
 }
+
+pub fn local_datetime(timestamp: u32) -> time::OffsetDateTime {
+    time::OffsetDateTime::from_unix_timestamp(i64::from(timestamp))
+        .unwrap()
+        .to_offset(time::UtcOffset::current_local_offset().unwrap())
+}
+
+pub fn format_timestamp(timestamp: u32) -> String {
+    local_datetime(timestamp)
+        .format(&time::macros::format_description!(
+            "[year]-[month]-[day] [hour]:[minute]"
+        ))
+        .unwrap()
+}
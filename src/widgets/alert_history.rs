@@ -0,0 +1,230 @@
+use crate::{
+    alerts::{HistoryEntry, Transition},
+    event_loop::Event,
+};
+use adw::prelude::*;
+use glib::subclass::prelude::*;
+use gtk::{prelude::*, CompositeTemplate};
+use uuid::Uuid;
+
+mod imp {
+    use super::*;
+    use gtk::subclass::prelude::*;
+    use once_cell::unsync::OnceCell;
+    use std::{
+        cell::{Cell, RefCell},
+        convert::TryFrom,
+    };
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/li/_5kw/BleWsGtk/alert_history.ui")]
+    pub struct AlertHistory {
+        #[template_child]
+        pub sensor_filter: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub sensor_filter_items: TemplateChild<gtk::StringList>,
+        #[template_child]
+        pub rule_filter: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub rule_filter_items: TemplateChild<gtk::StringList>,
+        #[template_child]
+        pub entry_list: TemplateChild<gtk::ListBox>,
+
+        pub evt_tx: OnceCell<glib::Sender<Event>>,
+        pub entries: RefCell<Vec<HistoryEntry>>,
+        /// sensors and rules selectable in the filters, after the "All" item
+        pub sensor_options: RefCell<Vec<Uuid>>,
+        pub rule_options: RefCell<Vec<String>>,
+        /// indices into `entries` of the displayed rows
+        pub shown: RefCell<Vec<usize>>,
+        /// set while the filter models get replaced
+        pub updating: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AlertHistory {
+        const NAME: &'static str = "BleWsAlertHistory";
+        type Type = super::AlertHistory;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for AlertHistory {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            for filter in &[&*self.sensor_filter, &*self.rule_filter] {
+                filter.connect_selected_notify(glib::clone!(@weak obj => move |_| {
+                    let self_ = Self::from_instance(&obj);
+                    if !self_.updating.get() {
+                        obj.refresh_list();
+                    }
+                }));
+            }
+
+            self.entry_list
+                .connect_row_activated(glib::clone!(@weak obj => move |_, row| {
+                    let self_ = Self::from_instance(&obj);
+                    let entries = self_.entries.borrow();
+                    let entry = usize::try_from(row.index())
+                        .ok()
+                        .and_then(|i| self_.shown.borrow().get(i).copied())
+                        .and_then(|i| entries.get(i));
+                    if let (Some(entry), Some(tx)) = (entry, self_.evt_tx.get()) {
+                        tx.send(Event::OpenAlert {
+                            id: entry.event.rule.sensor,
+                            time: entry.event.time,
+                        })
+                        .unwrap();
+                    }
+                }));
+
+            obj.refresh_filters();
+        }
+    }
+
+    impl BoxImpl for AlertHistory {}
+    impl WidgetImpl for AlertHistory {}
+}
+
+glib::wrapper! {
+    pub struct AlertHistory(ObjectSubclass<imp::AlertHistory>) @extends gtk::Box, gtk::Widget;
+}
+
+impl AlertHistory {
+    pub fn init(&self, tx: glib::Sender<Event>) {
+        let self_ = imp::AlertHistory::from_instance(self);
+        self_.evt_tx.set(tx).unwrap();
+    }
+
+    pub fn set_entries(&self, entries: &[HistoryEntry]) {
+        let self_ = imp::AlertHistory::from_instance(self);
+        self_.entries.replace(entries.to_vec());
+        self.refresh_filters();
+    }
+
+    pub fn push_entry(&self, entry: HistoryEntry) {
+        let self_ = imp::AlertHistory::from_instance(self);
+        self_.entries.borrow_mut().push(entry);
+        self.refresh_filters();
+    }
+
+    /// Rules and values are described in the current units.
+    pub fn refresh_units(&self) {
+        self.refresh_filters();
+    }
+
+    /// Rebuild the filter choices from the entries, keeping the current selection if possible.
+    fn refresh_filters(&self) {
+        let self_ = imp::AlertHistory::from_instance(self);
+        let selected_sensor = selected_option(&self_.sensor_filter, &self_.sensor_options);
+        let selected_rule = selected_option(&self_.rule_filter, &self_.rule_options);
+
+        let mut sensors: Vec<(Uuid, String)> = Vec::new();
+        let mut rules: Vec<String> = Vec::new();
+        // newest label wins
+        for entry in self_.entries.borrow().iter().rev() {
+            if !sensors.iter().any(|(id, _)| *id == entry.event.rule.sensor) {
+                sensors.push((entry.event.rule.sensor, entry.sensor_label.clone()));
+            }
+            let rule = entry.event.rule.kind.describe();
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+        sensors.sort_by(|a, b| a.1.cmp(&b.1));
+        rules.sort();
+
+        self_.updating.set(true);
+        let mut labels = vec!["All sensors"];
+        labels.extend(sensors.iter().map(|(_, label)| label.as_str()));
+        self_
+            .sensor_filter_items
+            .splice(0, self_.sensor_filter_items.n_items(), &labels);
+        let mut labels = vec!["All rules"];
+        labels.extend(rules.iter().map(String::as_str));
+        self_
+            .rule_filter_items
+            .splice(0, self_.rule_filter_items.n_items(), &labels);
+
+        let sensor_options = sensors.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        self_
+            .sensor_filter
+            .set_selected(option_position(selected_sensor.as_ref(), &sensor_options));
+        self_
+            .rule_filter
+            .set_selected(option_position(selected_rule.as_ref(), &rules));
+        self_.sensor_options.replace(sensor_options);
+        self_.rule_options.replace(rules);
+        self_.updating.set(false);
+
+        self.refresh_list();
+    }
+
+    fn refresh_list(&self) {
+        let self_ = imp::AlertHistory::from_instance(self);
+        while let Some(child) = self_.entry_list.first_child() {
+            self_.entry_list.remove(&child);
+        }
+
+        let sensor = selected_option(&self_.sensor_filter, &self_.sensor_options);
+        let rule = selected_option(&self_.rule_filter, &self_.rule_options);
+        let entries = self_.entries.borrow();
+        let mut shown = self_.shown.borrow_mut();
+        shown.clear();
+        for (i, entry) in entries.iter().enumerate().rev() {
+            let description = entry.event.rule.kind.describe();
+            if sensor.map_or(false, |sensor| sensor != entry.event.rule.sensor)
+                || rule.as_ref().map_or(false, |rule| *rule != description)
+            {
+                continue;
+            }
+
+            let row = adw::ActionRow::new();
+            let title = match entry.event.transition {
+                Transition::Fired => format!("Fired: {}", description),
+                Transition::Recovered => format!("Recovered: {}", description),
+            };
+            row.set_title(Some(&title));
+            let mut subtitle = format!(
+                "{} · {}",
+                crate::util::format_timestamp(entry.event.time),
+                entry.sensor_label
+            );
+            if let (Some(unit), Some(value)) = (entry.event.rule.kind.unit(), entry.event.value) {
+                subtitle.push_str(&format!(" · {}", unit.format_value(value)));
+            }
+            row.set_subtitle(Some(&subtitle));
+            row.set_activatable(true);
+            let icon = gtk::Image::from_icon_name(Some(match entry.event.transition {
+                Transition::Fired => "dialog-warning-symbolic",
+                Transition::Recovered => "emblem-ok-symbolic",
+            }));
+            row.add_prefix(&icon);
+            self_.entry_list.append(&row);
+            shown.push(i);
+        }
+    }
+}
+
+/// The option selected in `dropdown`, `None` if the leading "All" item is selected.
+fn selected_option<T: Clone>(
+    dropdown: &gtk::DropDown,
+    options: &std::cell::RefCell<Vec<T>>,
+) -> Option<T> {
+    let i = dropdown.selected().checked_sub(1)?;
+    options.borrow().get(i as usize).cloned()
+}
+
+fn option_position<T: PartialEq>(option: Option<&T>, options: &[T]) -> u32 {
+    option
+        .and_then(|option| options.iter().position(|o| o == option))
+        .map_or(0, |i| i as u32 + 1)
+}
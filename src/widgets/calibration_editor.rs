@@ -0,0 +1,292 @@
+use crate::{
+    local_store::{Calibration, Calibrations},
+    units::{Unit, UnitSystem},
+};
+use glib::subclass::prelude::*;
+use gtk::{prelude::*, CompositeTemplate};
+
+mod imp {
+    use super::*;
+    use adw::subclass::prelude::BinImpl;
+    use gtk::subclass::prelude::*;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(resource = "/li/_5kw/BleWsGtk/calibration_editor.ui")]
+    pub struct CalibrationEditor {
+        #[template_child]
+        pub temperature_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub pressure_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub temperature_offset: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub temperature_two_point: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub temperature_read1: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub temperature_actual1: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub temperature_read2: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub temperature_actual2: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub humidity_offset: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub humidity_two_point: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub humidity_read1: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub humidity_actual1: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub humidity_read2: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub humidity_actual2: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pressure_offset: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pressure_two_point: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub pressure_read1: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pressure_actual1: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pressure_read2: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pressure_actual2: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub raw_values_switch: TemplateChild<gtk::Switch>,
+
+        /// set while the widgets get filled programmatically
+        pub updating: Cell<bool>,
+        /// units the corrections are entered in
+        pub units: Cell<UnitSystem>,
+        pub changed_cb: RefCell<Option<Box<dyn Fn(Calibrations)>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CalibrationEditor {
+        const NAME: &'static str = "BleWsCalibrationEditor";
+        type Type = super::CalibrationEditor;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for CalibrationEditor {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            for row in obj.rows().iter() {
+                row.two_point
+                    .connect_toggled(glib::clone!(@weak obj => move |_| {
+                        obj.emit_changed();
+                    }));
+                for spin_button in row.spin_buttons().iter() {
+                    spin_button.connect_value_changed(glib::clone!(@weak obj => move |_| {
+                        obj.emit_changed();
+                    }));
+                }
+            }
+            crate::config::settings()
+                .bind("raw-values", &*self.raw_values_switch, "active")
+                .build();
+            obj.set_units(UnitSystem::current());
+        }
+    }
+
+    impl WidgetImpl for CalibrationEditor {}
+    impl BinImpl for CalibrationEditor {}
+}
+
+glib::wrapper! {
+    pub struct CalibrationEditor(ObjectSubclass<imp::CalibrationEditor>) @extends gtk::Widget, adw::Bin;
+}
+
+/// Raw offset that can be entered for `unit`, the values of the two points
+/// have the same range as the thresholds.
+fn raw_offset(unit: Unit) -> f64 {
+    match unit {
+        Unit::Temperature => 1000.,
+        Unit::Humidity => 2000.,
+        Unit::Pressure => 50000.,
+        _ => unreachable!("only measured units have a calibration"),
+    }
+}
+
+fn raw_range(unit: Unit) -> (f64, f64, f64) {
+    match unit {
+        Unit::Temperature => (-4000., 8500., 10.),
+        Unit::Humidity => (0., 10000., 10.),
+        Unit::Pressure => (300000., 1100000., 100.),
+        _ => unreachable!("only measured units have a calibration"),
+    }
+}
+
+struct Row<'a> {
+    unit: Unit,
+    offset: &'a gtk::SpinButton,
+    two_point: &'a gtk::CheckButton,
+    read1: &'a gtk::SpinButton,
+    actual1: &'a gtk::SpinButton,
+    read2: &'a gtk::SpinButton,
+    actual2: &'a gtk::SpinButton,
+}
+
+impl Row<'_> {
+    fn spin_buttons(&self) -> [&gtk::SpinButton; 5] {
+        [
+            self.offset,
+            self.read1,
+            self.actual1,
+            self.read2,
+            self.actual2,
+        ]
+    }
+
+    fn points(&self) -> [&gtk::SpinButton; 4] {
+        [self.read1, self.actual1, self.read2, self.actual2]
+    }
+}
+
+impl CalibrationEditor {
+    fn rows(&self) -> [Row<'_>; 3] {
+        let self_ = imp::CalibrationEditor::from_instance(self);
+        [
+            Row {
+                unit: Unit::Temperature,
+                offset: &self_.temperature_offset,
+                two_point: &self_.temperature_two_point,
+                read1: &self_.temperature_read1,
+                actual1: &self_.temperature_actual1,
+                read2: &self_.temperature_read2,
+                actual2: &self_.temperature_actual2,
+            },
+            Row {
+                unit: Unit::Humidity,
+                offset: &self_.humidity_offset,
+                two_point: &self_.humidity_two_point,
+                read1: &self_.humidity_read1,
+                actual1: &self_.humidity_actual1,
+                read2: &self_.humidity_read2,
+                actual2: &self_.humidity_actual2,
+            },
+            Row {
+                unit: Unit::Pressure,
+                offset: &self_.pressure_offset,
+                two_point: &self_.pressure_two_point,
+                read1: &self_.pressure_read1,
+                actual1: &self_.pressure_actual1,
+                read2: &self_.pressure_read2,
+                actual2: &self_.pressure_actual2,
+            },
+        ]
+    }
+
+    pub fn calibrations(&self) -> Calibrations {
+        let self_ = imp::CalibrationEditor::from_instance(self);
+        let units = self_.units.get();
+        let mut ret = Calibrations::default();
+        for row in self.rows().iter() {
+            let raw = |spin_button: &gtk::SpinButton| units.to_raw(row.unit, spin_button.value());
+            if let Some(calibration) = ret.get_mut(row.unit) {
+                *calibration = Calibration {
+                    offset: units.to_raw_delta(row.unit, row.offset.value()),
+                    two_point: if row.two_point.is_active() {
+                        Some([
+                            (raw(row.read1), raw(row.actual1)),
+                            (raw(row.read2), raw(row.actual2)),
+                        ])
+                    } else {
+                        None
+                    },
+                };
+            }
+        }
+        ret
+    }
+
+    /// Display `calibrations` without emitting a change.
+    pub fn set_calibrations(&self, calibrations: Calibrations) {
+        let self_ = imp::CalibrationEditor::from_instance(self);
+        let units = self_.units.get();
+        self_.updating.set(true);
+        for row in self.rows().iter() {
+            let calibration = calibrations.get(row.unit);
+            row.offset
+                .set_value(units.convert_delta(row.unit, calibration.offset));
+            row.two_point.set_active(calibration.two_point.is_some());
+            if let Some([(read1, actual1), (read2, actual2)]) = calibration.two_point {
+                for (spin_button, value) in row
+                    .points()
+                    .iter()
+                    .zip([read1, actual1, read2, actual2].iter())
+                {
+                    spin_button.set_value(units.convert(row.unit, *value));
+                }
+            }
+        }
+        self_.updating.set(false);
+    }
+
+    /// Enter the corrections in `units` from now on, keeping the current ones.
+    pub fn set_units(&self, units: UnitSystem) {
+        let self_ = imp::CalibrationEditor::from_instance(self);
+        let calibrations = self.calibrations();
+        self_.units.set(units);
+
+        self_.updating.set(true);
+        self_.temperature_label.set_label(&format!(
+            "Temperature ({})",
+            units.symbol(Unit::Temperature)
+        ));
+        self_
+            .pressure_label
+            .set_label(&format!("Pressure ({})", units.symbol(Unit::Pressure)));
+        for row in self.rows().iter() {
+            let offset = raw_offset(row.unit);
+            let (min, max, step) = raw_range(row.unit);
+            let adjustment = row.offset.adjustment();
+            adjustment.set_lower(-units.convert_delta(row.unit, offset));
+            adjustment.set_upper(units.convert_delta(row.unit, offset));
+            for spin_button in row.points().iter() {
+                let adjustment = spin_button.adjustment();
+                adjustment.set_lower(units.convert(row.unit, min));
+                adjustment.set_upper(units.convert(row.unit, max));
+            }
+            for spin_button in row.spin_buttons().iter() {
+                let adjustment = spin_button.adjustment();
+                adjustment.set_step_increment(units.convert_delta(row.unit, step));
+                adjustment.set_page_increment(units.convert_delta(row.unit, step * 10.));
+                spin_button.set_digits(units.precision(row.unit) as u32);
+            }
+        }
+        self_.updating.set(false);
+
+        self.set_calibrations(calibrations);
+    }
+
+    pub fn connect_changed<F>(&self, cb: F)
+    where
+        F: Fn(Calibrations) + 'static,
+    {
+        let self_ = imp::CalibrationEditor::from_instance(self);
+        *self_.changed_cb.borrow_mut() = Some(Box::new(cb));
+    }
+
+    fn emit_changed(&self) {
+        let self_ = imp::CalibrationEditor::from_instance(self);
+        if self_.updating.get() {
+            return;
+        }
+        if let Some(cb) = &*self_.changed_cb.borrow() {
+            cb(self.calibrations());
+        }
+    }
+}
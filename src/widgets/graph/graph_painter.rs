@@ -1,46 +1,59 @@
 use crate::{
-    data::{SharedTimeseries, Timeseries},
+    data::{Corrections, SharedTimeseries, Statistics, Timeseries},
+    local_store::{Threshold, Thresholds},
     sensor_obj::SensorObj,
+    units::{Unit, UnitSystem},
 };
-use ble_ws_api::data::{Celsius, RelativeHumidity};
 use gtk::{gdk, prelude::*, subclass::prelude::*};
-use plotters::{
-    coord::ranged1d::{AsRangedCoord, DefaultFormatting, Ranged},
-    prelude::DrawingBackend,
-    style::RGBColor,
-};
-use std::{cell::RefCell, convert::TryFrom, ops::Range};
+use plotters::{prelude::DrawingBackend, style::RGBColor};
+use std::{cell::RefCell, path::Path};
 use time::{macros::format_description, UtcOffset};
 
-#[derive(Debug, Eq, PartialEq, Clone, Copy, glib::GEnum)]
-#[repr(u8)]
-#[genum(type_name = "BleWsGraphUnit")]
-pub enum Unit {
-    Temperature = 0,
-    Humidity = 1,
-    Pressure = 2,
-}
-
-impl Default for Unit {
-    fn default() -> Self {
-        Self::Temperature
-    }
-}
-
 impl Unit {
-    pub fn as_usize(self) -> usize {
-        usize::from(self as u8)
-    }
-
-    pub fn label(self) -> &'static str {
+    /// Color used when a single series of this unit is drawn.
+    pub fn color(self) -> RGBColor {
         match self {
-            Unit::Temperature => "Temperature",
-            Unit::Humidity => "Humidity",
-            Unit::Pressure => "Pressure",
+            Unit::Temperature => RGBColor(178, 34, 34),
+            Unit::Humidity => RGBColor(106, 90, 205),
+            Unit::Pressure => RGBColor(0, 128, 0),
+            Unit::DewPoint => RGBColor(70, 130, 180),
+            Unit::AbsoluteHumidity => RGBColor(0, 139, 139),
+            Unit::HeatIndex => RGBColor(255, 140, 0),
+            Unit::VapourPressureDeficit => RGBColor(107, 142, 35),
         }
     }
 }
 
+const ALLOWED_COLOR: RGBColor = RGBColor(46, 139, 87);
+const OUT_OF_RANGE_COLOR: RGBColor = RGBColor(220, 20, 60);
+
+const SERIES_PALETTE: [RGBColor; 8] = [
+    RGBColor(31, 119, 180),
+    RGBColor(255, 127, 14),
+    RGBColor(44, 160, 44),
+    RGBColor(214, 39, 40),
+    RGBColor(148, 103, 189),
+    RGBColor(140, 86, 75),
+    RGBColor(227, 119, 194),
+    RGBColor(23, 190, 207),
+];
+
+/// Color for the `i`th series of a graph showing multiple sensors.
+pub fn series_color(i: usize) -> RGBColor {
+    SERIES_PALETTE[i % SERIES_PALETTE.len()]
+}
+
+/// One line drawn by the painter.
+#[derive(Clone, Default)]
+pub struct Series {
+    pub timeseries: SharedTimeseries,
+    pub label: Option<String>,
+    pub color: Option<RGBColor>,
+    /// Seconds added to every timestamp before drawing
+    pub shift: u32,
+    pub dimmed: bool,
+}
+
 mod imp {
     use super::*;
     use glib::SignalHandlerId;
@@ -48,13 +61,15 @@ mod imp {
 
     #[derive(Default)]
     pub struct GraphPainter {
-        pub timeseries: RefCell<SharedTimeseries>,
+        pub series: RefCell<Vec<Series>>,
+        pub overlay: RefCell<Option<Series>>,
         pub unit: Cell<Unit>,
         pub grid_color: Cell<plotters::style::RGBColor>,
-        pub obj: RefCell<Option<SensorObj>>,
-        pub live_binding: Cell<Option<SignalHandlerId>>,
+        pub live_bindings: RefCell<Vec<(SensorObj, SignalHandlerId)>>,
         pub bounds: Cell<Option<(u32, u32)>>,
         pub time_format: Cell<TimeFormat>,
+        pub thresholds: Cell<Thresholds>,
+        pub settings_bindings: RefCell<Vec<(SensorObj, SignalHandlerId)>>,
     }
 
     #[glib::object_subclass]
@@ -71,6 +86,73 @@ mod imp {
         }
     }
 
+    impl GraphPainter {
+        /// Draw the current state with `unit` onto `ctx`, does nothing if
+        /// there's no data.
+        pub fn render(
+            &self,
+            ctx: &gtk::cairo::Context,
+            bounding_box: (u32, u32),
+            unit: Unit,
+            grid_color: RGBColor,
+            title: Option<&str>,
+        ) -> Result<(), anyhow::Error> {
+            let series = self.series.borrow();
+            let overlay = self.overlay.borrow();
+            let borrowed = series
+                .iter()
+                .chain(overlay.iter())
+                .filter_map(|series| {
+                    let timeseries = (*series.timeseries.0).as_ref()?.borrow();
+                    if timeseries.is_empty() {
+                        None
+                    } else {
+                        Some((series, timeseries))
+                    }
+                })
+                .collect::<Vec<_>>();
+            if borrowed.is_empty() {
+                return Ok(());
+            }
+
+            let plot_series = borrowed
+                .iter()
+                .map(|(series, timeseries)| PlotSeries {
+                    timeseries: &**timeseries,
+                    label: series.label.as_deref(),
+                    color: series.color,
+                    shift: series.shift,
+                    dimmed: series.dimmed,
+                })
+                .collect::<Vec<_>>();
+
+            plot(
+                ctx,
+                &plot_series,
+                bounding_box,
+                PlotOptions {
+                    unit,
+                    bounds: self.bounds.get(),
+                    time_format: self.time_format.get(),
+                    grid_color,
+                    title,
+                    local_offset: UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC),
+                    threshold: self.thresholds.get().get(unit),
+                },
+            )
+        }
+
+        fn has_data(&self) -> bool {
+            self.series
+                .borrow()
+                .iter()
+                .any(|series| match &*series.timeseries.0 {
+                    Some(timeseries) => !timeseries.borrow().is_empty(),
+                    None => false,
+                })
+        }
+    }
+
     impl PaintableImpl for GraphPainter {
         fn snapshot(
             &self,
@@ -79,139 +161,177 @@ mod imp {
             width: f64,
             height: f64,
         ) {
-            let timeseries = self.timeseries.borrow();
-            match &*timeseries.0 {
-                Some(timeseries) => {
-                    let timeseries = timeseries.borrow();
-                    if timeseries.is_empty() {
-                        return;
-                    }
-                    let snapshot = snapshot.downcast_ref::<gtk::Snapshot>().unwrap();
-                    let bounding_box = (approx_to(width), approx_to(height));
-
-                    let ctx = snapshot
-                        .append_cairo(&gtk::graphene::Rect::new(
-                            0.,
-                            0.,
-                            width as f32,
-                            height as f32,
-                        ))
-                        .expect("Failed acquiring cairo context");
-
-                    if let Err(e) = plot(
-                        &ctx,
-                        self.grid_color.get(),
-                        &timeseries,
-                        self.unit.get(),
-                        self.bounds.get(),
-                        bounding_box,
-                        self.time_format.get(),
-                    ) {
-                        tracing::error!("{}", e);
-                    }
-                }
-                _ => (),
+            if !self.has_data() {
+                return;
+            }
+            let snapshot = snapshot.downcast_ref::<gtk::Snapshot>().unwrap();
+            let bounding_box = (approx_to(width), approx_to(height));
+
+            let ctx = snapshot
+                .append_cairo(&gtk::graphene::Rect::new(
+                    0.,
+                    0.,
+                    width as f32,
+                    height as f32,
+                ))
+                .expect("Failed acquiring cairo context");
+
+            if let Err(e) = self.render(
+                &ctx,
+                bounding_box,
+                self.unit.get(),
+                self.grid_color.get(),
+                None,
+            ) {
+                tracing::error!("{}", e);
             }
         }
     }
 }
 
+/// A borrowed [`Series`] ready to be plotted.
+pub struct PlotSeries<'a> {
+    pub timeseries: &'a Timeseries,
+    pub label: Option<&'a str>,
+    pub color: Option<RGBColor>,
+    pub shift: u32,
+    pub dimmed: bool,
+}
+
+/// Everything besides the data that decides what a plot looks like.
+#[derive(Clone, Copy)]
+pub struct PlotOptions<'a> {
+    pub unit: Unit,
+    pub bounds: Option<(u32, u32)>,
+    pub time_format: TimeFormat,
+    pub grid_color: RGBColor,
+    pub title: Option<&'a str>,
+    /// Offset the time axis is labeled in
+    pub local_offset: UtcOffset,
+    pub threshold: Threshold,
+}
+
 fn plot(
     ctx: &gtk::cairo::Context,
-    grid_color: RGBColor,
-    timeseries: &Timeseries,
-    unit: Unit,
-    bounds: Option<(u32, u32)>,
+    series: &[PlotSeries<'_>],
     bounding_box: (u32, u32),
-    time_format: TimeFormat,
+    options: PlotOptions<'_>,
 ) -> Result<(), anyhow::Error> {
     use plotters::prelude::*;
-    let root = plotters_cairo::CairoBackend::new(ctx, (bounding_box.0, bounding_box.1))
+    let PlotOptions {
+        unit,
+        bounds,
+        time_format,
+        grid_color,
+        title,
+        local_offset,
+        threshold,
+    } = options;
+    let mut root = plotters_cairo::CairoBackend::new(ctx, (bounding_box.0, bounding_box.1))
         .into_drawing_area();
+    if let Some(title) = title {
+        root = root.titled(title, ("sans-serif", 20).into_font().color(&grid_color))?;
+    }
 
-    let (time, (start, end)) = match bounds {
-        None => (timeseries.time(), (0, timeseries.time().len())),
-        Some((lower, upper)) => {
-            let time = timeseries.time();
-            let start = match time.iter().position(|t| *t >= lower) {
-                Some(i) => i,
-                // empty, nothing to draw
-                None => return Ok(()),
+    let lines = series
+        .iter()
+        .filter_map(|series| {
+            let range = series.timeseries.bounds_range(bounds.map(|(lower, upper)| {
+                (
+                    lower.saturating_sub(series.shift),
+                    upper.saturating_sub(series.shift),
+                )
+            }));
+            if range.is_empty() {
+                // nothing to draw
+                return None;
+            }
+            let time = series.timeseries.time();
+            let span = time[range.end - 1] - time[range.start];
+            let (time, values, band) = match envelope_bucket(span) {
+                Some(bucket) => {
+                    let aggregated = series.timeseries.aggregate_range(range, bucket);
+                    let column = aggregated.column(unit);
+                    (
+                        aggregated.time.clone(),
+                        column.mean.clone(),
+                        Some((column.min.clone(), column.max.clone())),
+                    )
+                }
+                None => (
+                    time[range.clone()].to_vec(),
+                    series.timeseries.column(unit)[range].to_vec(),
+                    None,
+                ),
             };
-            let sliced = &time[start..];
-            let end = sliced
-                .iter()
-                .position(|t| *t >= upper)
-                .unwrap_or(sliced.len());
 
-            (&sliced[..end], (start, start + end))
-        }
-    };
+            Some(Line {
+                time: time
+                    .into_iter()
+                    .map(|t| t.saturating_add(series.shift))
+                    .collect(),
+                values,
+                band,
+                color: series.color.unwrap_or_else(|| unit.color()),
+                dimmed: series.dimmed,
+                label: series.label,
+            })
+        })
+        .collect::<Vec<_>>();
 
-    match unit {
-        Unit::Temperature => {
-            // FIXME: fork plotters and impl Rangedi16
-            let hoopla = timeseries
-                .temperature()
-                .iter()
-                .copied()
-                .map(i32::from)
-                .collect::<Vec<_>>();
-            draw_graph(
-                &root,
-                &time,
-                &hoopla[start..end],
-                grid_color,
-                FormatSpec {
-                    color: RGBColor(178, 34, 34),
-                    formatter: &|temp| Celsius::try_from(*temp as i16).unwrap().to_string(),
-                    margin_px: 75,
-                    time_format,
-                },
-            )?;
-        }
+    if lines.is_empty() {
+        return Ok(());
+    }
 
-        Unit::Humidity => {
-            // FIXME: fork plotters and impl Rangedu16
-            draw_graph(
-                &root,
-                &time,
-                &timeseries.humidity()[start..end],
-                grid_color,
-                FormatSpec {
-                    margin_px: 75,
-                    formatter: &|humidity| {
-                        RelativeHumidity::try_from(*humidity as u16)
-                            .unwrap()
-                            .to_string()
-                    },
-                    color: RGBColor(106, 90, 205),
-                    time_format,
-                },
-            )?;
-        }
+    draw_graph(
+        &root,
+        &lines,
+        grid_color,
+        FormatSpec {
+            margin_px: 75,
+            formatter: &|value| unit.format_value(*value),
+            time_format,
+            local_offset,
+            threshold,
+        },
+    )
+}
 
-        Unit::Pressure => {
-            draw_graph(
-                &root,
-                &time,
-                &timeseries.pressure()[start..end],
-                grid_color,
-                FormatSpec {
-                    margin_px: 75,
-                    formatter: &|pressure| {
-                        let floating = pressure % 1000;
-                        let other = pressure / 1000;
-                        format!("{}.{:<2}hPa", floating, other)
-                    },
-                    color: RGBColor(0, 128, 0),
-                    time_format,
-                },
-            )?;
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+    Pdf,
+}
+
+impl ImageFormat {
+    /// In the order of the export dialog filters.
+    pub const ALL: [ImageFormat; 3] = [ImageFormat::Png, ImageFormat::Svg, ImageFormat::Pdf];
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.extension() == extension)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Svg => "svg",
+            Self::Pdf => "pdf",
         }
     }
 
-    Ok(())
+    /// Name of the file filter.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Png => "PNG image",
+            Self::Svg => "SVG image",
+            Self::Pdf => "PDF document",
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -226,37 +346,88 @@ impl Default for TimeFormat {
     }
 }
 
-struct FormatSpec<'a, N, C> {
+struct FormatSpec<'a> {
     margin_px: u32,
-    formatter: &'a dyn Fn(&N) -> String,
-    color: C,
+    formatter: &'a dyn Fn(&f64) -> String,
     time_format: TimeFormat,
+    local_offset: UtcOffset,
+    threshold: Threshold,
+}
+
+/// Bucket size used to summarize a range spanning `span` seconds, or `None`
+/// if the range is short enough to draw every sample.
+fn envelope_bucket(span: u32) -> Option<u32> {
+    const HOUR: u32 = 60 * 60;
+    const DAY: u32 = 24 * HOUR;
+    if span < 3 * DAY {
+        None
+    } else if span < 60 * DAY {
+        Some(HOUR)
+    } else {
+        Some(DAY)
+    }
 }
 
-fn draw_graph<DB, N, GridColor, GraphColor>(
+struct Line<'a> {
+    time: Vec<u32>,
+    values: Vec<f64>,
+    /// min and max per sample, drawn as a filled area around `values`
+    band: Option<(Vec<f64>, Vec<f64>)>,
+    color: RGBColor,
+    dimmed: bool,
+    label: Option<&'a str>,
+}
+
+fn draw_graph<DB>(
     area: &plotters::prelude::DrawingArea<DB, plotters::coord::Shift>,
-    timestamps: &[u32],
-    nums: &[N],
-    grid_color: GridColor,
-    spec: FormatSpec<'_, N, GraphColor>,
+    lines: &[Line<'_>],
+    grid_color: RGBColor,
+    spec: FormatSpec<'_>,
 ) -> Result<(), anyhow::Error>
 where
     DB: DrawingBackend,
     <DB as DrawingBackend>::ErrorType: 'static,
-    N: Ord + Copy + std::fmt::Debug + 'static,
-    Range<N>: AsRangedCoord<Value = N>,
-    <Range<N> as AsRangedCoord>::CoordDescType:
-        Ranged<ValueType = N, FormatOption = DefaultFormatting>,
-    GridColor: plotters::prelude::Color,
-    GraphColor: plotters::prelude::Color,
 {
     use plotters::prelude::*;
     tracing::trace!("draw_graph called");
 
-    let first = *timestamps.first().unwrap();
-    let last = *timestamps.last().unwrap();
+    let mut first = lines.iter().map(|line| line.time[0]).min().unwrap();
+    let mut last = lines
+        .iter()
+        .map(|line| line.time[line.time.len() - 1])
+        .max()
+        .unwrap();
 
-    let (min, max) = minmax(&nums).unwrap();
+    let (mut min, mut max) = lines
+        .iter()
+        .filter_map(|line| match &line.band {
+            Some((lower, upper)) => Some((minmax(lower)?.0, minmax(upper)?.1)),
+            None => minmax(&line.values),
+        })
+        .fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(min, max), (lo, hi)| (min.min(lo), max.max(hi)),
+        );
+
+    let threshold = spec.threshold;
+    // always show the limits
+    if let Some(lower) = threshold.lower {
+        min = min.min(lower);
+    }
+    if let Some(upper) = threshold.upper {
+        max = max.max(upper);
+    }
+
+    // plotters can't map anything onto empty ranges
+    if first == last {
+        first = first.saturating_sub(60);
+        last = last.saturating_add(60);
+    }
+    if min == max {
+        let padding = (min.abs() * 0.05).max(1.);
+        min -= padding;
+        max += padding;
+    }
 
     let mut chart = ChartBuilder::on(area)
         .margin(5)
@@ -265,8 +436,10 @@ where
         .y_label_area_size(spec.margin_px)
         .build_cartesian_2d(first..last, min..max)?;
 
-    let today = crate::util::now_local().date();
-    let local_offset = UtcOffset::current_local_offset().unwrap();
+    let local_offset = spec.local_offset;
+    let today = time::OffsetDateTime::now_utc()
+        .to_offset(local_offset)
+        .date();
 
     chart
         .configure_mesh()
@@ -292,12 +465,81 @@ where
         .y_label_formatter(spec.formatter)
         .draw()?;
 
-    chart.draw_series(LineSeries::new(
-        timestamps.iter().copied().zip(nums.iter().copied()),
-        &spec.color,
-    ))?;
+    if threshold.is_set() {
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [
+                (first, threshold.lower.unwrap_or(min)),
+                (last, threshold.upper.unwrap_or(max)),
+            ],
+            ALLOWED_COLOR.mix(0.1).filled(),
+        )))?;
+        let limit_style = ALLOWED_COLOR.mix(0.8).stroke_width(2);
+        chart.draw_series(
+            threshold
+                .lower
+                .iter()
+                .chain(threshold.upper.iter())
+                .map(|&limit| PathElement::new(vec![(first, limit), (last, limit)], limit_style)),
+        )?;
+    }
 
-    chart.configure_series_labels().draw()?;
+    for line in lines {
+        if let Some((lower, upper)) = &line.band {
+            let area = line
+                .time
+                .iter()
+                .copied()
+                .zip(upper.iter().copied())
+                .chain(line.time.iter().copied().zip(lower.iter().copied()).rev())
+                .collect::<Vec<_>>();
+            let alpha = if line.dimmed { 0.1 } else { 0.25 };
+            chart.draw_series(std::iter::once(Polygon::new(
+                area,
+                line.color.mix(alpha).filled(),
+            )))?;
+        }
+
+        let style = if line.dimmed {
+            ShapeStyle::from(&line.color.mix(0.4))
+        } else {
+            ShapeStyle::from(&line.color)
+        };
+        let drawn = chart.draw_series(LineSeries::new(
+            line.time.iter().copied().zip(line.values.iter().copied()),
+            style,
+        ))?;
+        if let Some(label) = line.label {
+            drawn
+                .label(label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style));
+        }
+
+        if threshold.is_set() && !line.dimmed {
+            let out_of_range = OUT_OF_RANGE_COLOR.stroke_width(2);
+            chart.draw_series(
+                line.time
+                    .windows(2)
+                    .zip(line.values.windows(2))
+                    .filter(|(_, values)| {
+                        !threshold.contains(values[0]) || !threshold.contains(values[1])
+                    })
+                    .map(|(time, values)| {
+                        PathElement::new(
+                            vec![(time[0], values[0]), (time[1], values[1])],
+                            out_of_range,
+                        )
+                    }),
+            )?;
+        }
+    }
+
+    if lines.iter().any(|line| line.label.is_some()) {
+        chart
+            .configure_series_labels()
+            .label_font(&grid_color)
+            .border_style(&grid_color)
+            .draw()?;
+    }
 
     Ok(())
 }
@@ -317,21 +559,13 @@ fn approx_to(n: f64) -> u32 {
     }
 }
 
-pub fn minmax<T>(a: &[T]) -> Option<(T, T)>
-where
-    T: Ord + Copy,
-{
-    let mut min = *a.get(0)?;
-    let mut max = min;
-    for n in a.iter().skip(1).copied() {
-        if n < min {
-            min = n;
-        } else if n > max {
-            max = n;
-        }
-    }
-
-    Some((min, max))
+fn minmax(a: &[f64]) -> Option<(f64, f64)> {
+    let first = *a.get(0)?;
+    Some(
+        a.iter()
+            .skip(1)
+            .fold((first, first), |(min, max), &n| (min.min(n), max.max(n))),
+    )
 }
 
 glib::wrapper! {
@@ -354,6 +588,24 @@ impl GraphPainter {
 pub enum Data {
     Live(SensorObj),
     Static(Timeseries),
+    /// Live data of multiple sensors, each drawn with its own color and
+    /// labeled in a legend
+    Comparison(Vec<(SensorObj, RGBColor)>),
+}
+
+fn sensor_series(obj: &SensorObj, color: RGBColor) -> Series {
+    Series {
+        timeseries: obj.timeseries(),
+        label: Some(
+            obj.data()
+                .label
+                .borrow()
+                .clone()
+                .unwrap_or_else(|| obj.id().to_string()),
+        ),
+        color: Some(color),
+        ..Default::default()
+    }
 }
 
 impl GraphPainter {
@@ -371,36 +623,183 @@ impl GraphPainter {
 
     pub fn set_displayed_data(&self, data: Option<Data>) {
         let self_ = imp::GraphPainter::from_instance(&self);
-        if let Some(obj) = self_.obj.take() {
-            if let Some(bind) = self_.live_binding.take() {
-                obj.disconnect(bind);
-            }
+        for (obj, bind) in self_.live_bindings.borrow_mut().drain(..) {
+            obj.disconnect(bind);
         }
         self_.bounds.set(None);
+        match &data {
+            Some(Data::Live(obj)) => self.track_settings(Some(obj)),
+            // thresholds still belong to the sensor the static data is from
+            Some(Data::Static(_)) => (),
+            Some(Data::Comparison(_)) | None => self.track_settings(None),
+        }
         match data {
             Some(Data::Live(obj)) => {
                 // TODO: use binding instead
-                self_.timeseries.replace(obj.timeseries());
+                self_.series.replace(vec![Series {
+                    timeseries: obj.timeseries(),
+                    ..Default::default()
+                }]);
                 let binding = obj.connect_notify_local(
                     Some("live-timeseries"),
                     glib::clone!(@weak self as this => move |obj, _param_spec| {
                         let self_ = imp::GraphPainter::from_instance(&this);
-                        self_.timeseries.replace(obj.timeseries());
+                        self_.series.borrow_mut()[0].timeseries = obj.timeseries();
                         this.invalidate_contents();
                     }),
                 );
-                self_.live_binding.replace(Some(binding));
-                self_.obj.replace(Some(obj));
+                self_.live_bindings.borrow_mut().push((obj, binding));
                 self_.time_format.set(TimeFormat::TimeOnly);
             }
             Some(Data::Static(timeseries)) => {
-                self_.timeseries.replace(SharedTimeseries::new(timeseries));
+                self_.series.replace(vec![Series {
+                    timeseries: SharedTimeseries::new(timeseries),
+                    ..Default::default()
+                }]);
                 self_.time_format.set(TimeFormat::DateTime);
             }
+            Some(Data::Comparison(sensors)) => {
+                self_.series.replace(
+                    sensors
+                        .iter()
+                        .map(|(obj, color)| sensor_series(obj, *color))
+                        .collect(),
+                );
+                let mut live_bindings = self_.live_bindings.borrow_mut();
+                for (i, (obj, _)) in sensors.into_iter().enumerate() {
+                    let binding = obj.connect_notify_local(
+                        Some("live-timeseries"),
+                        glib::clone!(@weak self as this => move |obj, _param_spec| {
+                            let self_ = imp::GraphPainter::from_instance(&this);
+                            self_.series.borrow_mut()[i].timeseries = obj.timeseries();
+                            this.invalidate_contents();
+                        }),
+                    );
+                    live_bindings.push((obj, binding));
+                }
+                self_.time_format.set(TimeFormat::TimeOnly);
+            }
             None => {
-                self_.timeseries.replace(SharedTimeseries::empty());
+                self_.series.replace(Vec::new());
+            }
+        }
+        self.invalidate_contents();
+    }
+
+    /// Statistics of `unit` for the part of the primary series that is
+    /// currently visible.
+    pub fn statistics(&self, unit: Unit) -> Option<Statistics> {
+        let self_ = imp::GraphPainter::from_instance(&self);
+        let series = self_.series.borrow();
+        let timeseries = (*series.first()?.timeseries.0).as_ref()?.borrow();
+        timeseries.statistics(unit, self_.bounds.get())
+    }
+
+    /// Draw the data with `unit` instead of the displayed unit onto `ctx`,
+    /// e.g. for printing.
+    pub fn render_unit(
+        &self,
+        ctx: &gtk::cairo::Context,
+        size: (u32, u32),
+        unit: Unit,
+        grid_color: RGBColor,
+    ) -> Result<(), anyhow::Error> {
+        let self_ = imp::GraphPainter::from_instance(&self);
+        self_.render(ctx, size, unit, grid_color, None)
+    }
+
+    /// Render the current unit, bounds and data to a file with a title, the
+    /// size is independent of any widget displaying this painter.
+    pub fn export_image(
+        &self,
+        path: &Path,
+        format: ImageFormat,
+        (width, height): (u32, u32),
+        title: &str,
+    ) -> Result<(), anyhow::Error> {
+        use gtk::cairo::{Context, Format, ImageSurface, PdfSurface, SvgSurface};
+        let self_ = imp::GraphPainter::from_instance(&self);
+        let draw = |ctx: &Context| {
+            ctx.set_source_rgb(1., 1., 1.);
+            ctx.paint()?;
+            self_.render(
+                ctx,
+                (width, height),
+                self_.unit.get(),
+                RGBColor(0, 0, 0),
+                Some(title),
+            )
+        };
+
+        match format {
+            ImageFormat::Png => {
+                let surface = ImageSurface::create(Format::ARgb32, width as i32, height as i32)?;
+                draw(&Context::new(&surface)?)?;
+                let mut file = std::fs::File::create(path)?;
+                surface.write_to_png(&mut file)?;
+            }
+            ImageFormat::Svg => {
+                let surface = SvgSurface::new(f64::from(width), f64::from(height), Some(path))?;
+                draw(&Context::new(&surface)?)?;
+                surface.finish();
+            }
+            ImageFormat::Pdf => {
+                let surface = PdfSurface::new(f64::from(width), f64::from(height), path)?;
+                draw(&Context::new(&surface)?)?;
+                surface.finish();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draw the thresholds of `sensor`, apply its corrections and follow
+    /// changes to them.
+    pub fn track_settings(&self, sensor: Option<&SensorObj>) {
+        let self_ = imp::GraphPainter::from_instance(&self);
+        for (obj, binding) in self_.settings_bindings.borrow_mut().drain(..) {
+            obj.disconnect(binding);
+        }
+        match sensor {
+            Some(sensor) => {
+                self_.thresholds.set(sensor.settings().thresholds);
+                let binding = sensor.connect_notify_local(
+                    Some("settings"),
+                    glib::clone!(@weak self as this => move |obj, _param_spec| {
+                        let self_ = imp::GraphPainter::from_instance(&this);
+                        self_.thresholds.set(obj.settings().thresholds);
+                        this.invalidate_contents();
+                    }),
+                );
+                let corrections_binding = sensor.connect_notify_local(
+                    Some("corrections"),
+                    glib::clone!(@weak self as this => move |obj, _param_spec| {
+                        this.set_corrections(obj.corrections());
+                    }),
+                );
+                self_.settings_bindings.replace(vec![
+                    (sensor.clone(), binding),
+                    (sensor.clone(), corrections_binding),
+                ]);
+            }
+            None => self_.thresholds.set(Thresholds::default()),
+        }
+        self.invalidate_contents();
+    }
+
+    /// Correct all displayed data with `corrections`, the live data is
+    /// corrected by its sensor already.
+    pub fn set_corrections(&self, corrections: Corrections) {
+        let self_ = imp::GraphPainter::from_instance(&self);
+        let series = self_.series.borrow();
+        let overlay = self_.overlay.borrow();
+        for series in series.iter().chain(overlay.iter()) {
+            if let Some(timeseries) = &*series.timeseries.0 {
+                timeseries.borrow_mut().set_corrections(corrections);
             }
         }
+        drop(series);
+        drop(overlay);
         self.invalidate_contents();
     }
 
@@ -410,9 +809,270 @@ impl GraphPainter {
         self.invalidate_contents();
     }
 
+    /// Draw `timeseries` dimmed and moved `shift` seconds into the future
+    /// on top of the displayed data.
+    pub fn set_overlay(&self, overlay: Option<(Timeseries, u32)>, label: &str) {
+        let self_ = imp::GraphPainter::from_instance(&self);
+        self_
+            .overlay
+            .replace(overlay.map(|(timeseries, shift)| Series {
+                timeseries: SharedTimeseries::new(timeseries),
+                label: Some(label.to_owned()),
+                color: None,
+                shift,
+                dimmed: true,
+            }));
+        self.invalidate_contents();
+    }
+
     pub fn set_bounds(&self, bounds: Option<(u32, u32)>) {
         let self_ = imp::GraphPainter::from_instance(&self);
         self_.bounds.set(bounds);
         self.invalidate_contents();
     }
 }
+
+/// Golden image tests for [`plot`].
+///
+/// Snapshots live in `tests/snapshots`, a missing one fails its test. Run the
+/// tests with `BLESS_SNAPSHOTS=1` to write them, also after intended changes
+/// to the rendering.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TimeseriesBuilder;
+    use gtk::cairo::{Context, Format, ImageSurface};
+    use std::{fs::File, path::PathBuf};
+
+    const SIZE: (u32, u32) = (640, 360);
+    // 2021-08-26 17:46:40 UTC
+    const START: u32 = 1_630_000_000;
+    // allowed difference per color channel, fonts differ slightly between machines
+    const CHANNEL_TOLERANCE: u8 = 48;
+    // allowed fraction of pixels exceeding the channel tolerance
+    const PIXEL_TOLERANCE: f64 = 0.01;
+
+    fn fixture(temperature: &[i16], humidity: &[u32], pressure: &[u32]) -> Timeseries {
+        TimeseriesBuilder::default()
+            .time(
+                (0..temperature.len() as u32)
+                    .map(|i| START + i * 600)
+                    .collect(),
+            )
+            .temperature(temperature.to_vec())
+            .humidity(humidity.to_vec())
+            .pressure(pressure.to_vec())
+            .build()
+            .unwrap()
+    }
+
+    fn day() -> Timeseries {
+        let n = 144;
+        fixture(
+            &(0..n)
+                .map(|i| 2000 + ((i as f64 / 10.).sin() * 500.) as i16)
+                .collect::<Vec<_>>(),
+            &(0..n)
+                .map(|i| 4500 + ((i as f64 / 7.).cos() * 1500.) as u32)
+                .collect::<Vec<_>>(),
+            &(0..n).map(|i| 1_013_250 - i * 40).collect::<Vec<_>>(),
+        )
+    }
+
+    fn render(
+        timeseries: &Timeseries,
+        unit: Unit,
+        time_format: TimeFormat,
+        bounds: Option<(u32, u32)>,
+    ) -> ImageSurface {
+        let surface = ImageSurface::create(Format::ARgb32, SIZE.0 as i32, SIZE.1 as i32).unwrap();
+        {
+            let ctx = Context::new(&surface).unwrap();
+            ctx.set_source_rgb(1., 1., 1.);
+            ctx.paint().unwrap();
+            plot(
+                &ctx,
+                &[PlotSeries {
+                    timeseries,
+                    label: None,
+                    color: None,
+                    shift: 0,
+                    dimmed: false,
+                }],
+                SIZE,
+                PlotOptions {
+                    unit,
+                    bounds,
+                    time_format,
+                    grid_color: RGBColor(0, 0, 0),
+                    title: None,
+                    local_offset: UtcOffset::UTC,
+                    threshold: Threshold::default(),
+                },
+            )
+            .unwrap();
+        }
+        surface
+    }
+
+    fn snapshot_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("snapshots")
+            .join(format!("{}.png", name))
+    }
+
+    /// Compare `actual` with the committed snapshot `name`, run the tests with
+    /// `BLESS_SNAPSHOTS` set to write the snapshots instead.
+    fn assert_snapshot(name: &str, mut actual: ImageSurface) {
+        let path = snapshot_path(name);
+        if std::env::var_os("BLESS_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            actual
+                .write_to_png(&mut File::create(&path).unwrap())
+                .unwrap();
+            return;
+        }
+
+        let mut file = File::open(&path).unwrap_or_else(|e| {
+            panic!(
+                "Missing snapshot {}, create it with BLESS_SNAPSHOTS=1: {}",
+                path.display(),
+                e
+            )
+        });
+        let mut expected = ImageSurface::create_from_png(&mut file)
+            .unwrap_or_else(|e| panic!("Can't read snapshot {}: {}", path.display(), e));
+        assert_eq!(
+            (expected.width(), expected.height()),
+            (actual.width(), actual.height()),
+            "Snapshot {} has a different size",
+            name
+        );
+
+        actual.flush();
+        let actual = actual.data().unwrap();
+        let expected = expected.data().unwrap();
+        let differing = actual
+            .chunks_exact(4)
+            .zip(expected.chunks_exact(4))
+            .filter(|(a, b)| {
+                a.iter().zip(b.iter()).any(|(a, b)| {
+                    (i16::from(*a) - i16::from(*b)).abs() > i16::from(CHANNEL_TOLERANCE)
+                })
+            })
+            .count();
+        let fraction = differing as f64 / (actual.len() / 4) as f64;
+        assert!(
+            fraction <= PIXEL_TOLERANCE,
+            "Snapshot {} differs in {:.2}% of pixels",
+            name,
+            fraction * 100.
+        );
+    }
+
+    fn time_format_name(time_format: TimeFormat) -> &'static str {
+        match time_format {
+            TimeFormat::TimeOnly => "time_only",
+            TimeFormat::DateTime => "date_time",
+        }
+    }
+
+    #[test]
+    fn units_and_time_formats() {
+        let timeseries = day();
+        for unit in Unit::ALL.iter() {
+            for time_format in &[TimeFormat::TimeOnly, TimeFormat::DateTime] {
+                let name = format!("{}_{}", unit.name(), time_format_name(*time_format));
+                assert_snapshot(&name, render(&timeseries, *unit, *time_format, None));
+            }
+        }
+    }
+
+    #[test]
+    fn single_sample() {
+        let timeseries = fixture(&[2150], &[5000], &[1_013_250]);
+        for unit in &[Unit::Temperature, Unit::Humidity, Unit::Pressure] {
+            let name = format!("single_sample_{}", unit.label().to_lowercase());
+            assert_snapshot(
+                &name,
+                render(&timeseries, *unit, TimeFormat::DateTime, None),
+            );
+        }
+    }
+
+    #[test]
+    fn constant_values() {
+        let timeseries = fixture(&[2150; 20], &[10000; 20], &[1_013_250; 20]);
+        for unit in &[Unit::Temperature, Unit::Humidity, Unit::Pressure] {
+            let name = format!("constant_{}", unit.label().to_lowercase());
+            assert_snapshot(
+                &name,
+                render(&timeseries, *unit, TimeFormat::DateTime, None),
+            );
+        }
+    }
+
+    #[test]
+    fn negative_temperatures() {
+        let temperature = (0..48).map(|i| -1500 + i * 25).collect::<Vec<_>>();
+        let timeseries = fixture(&temperature, &[3000; 48], &[1_000_000; 48]);
+        assert_snapshot(
+            "negative_temperature",
+            render(&timeseries, Unit::Temperature, TimeFormat::DateTime, None),
+        );
+    }
+
+    #[test]
+    fn bounds() {
+        let timeseries = day();
+        let last = *timeseries.time().last().unwrap();
+        assert_snapshot(
+            "bounds_partial",
+            render(
+                &timeseries,
+                Unit::Temperature,
+                TimeFormat::TimeOnly,
+                Some((START + 6 * 60 * 60, u32::MAX)),
+            ),
+        );
+        // nothing inside the bounds, must not panic and draw nothing
+        assert_snapshot(
+            "bounds_outside",
+            render(
+                &timeseries,
+                Unit::Temperature,
+                TimeFormat::TimeOnly,
+                Some((last + 1, u32::MAX)),
+            ),
+        );
+        assert_snapshot(
+            "bounds_before",
+            render(
+                &timeseries,
+                Unit::Humidity,
+                TimeFormat::TimeOnly,
+                Some((0, START)),
+            ),
+        );
+    }
+
+    #[test]
+    fn empty_timeseries() {
+        // without data nothing gets drawn over the white background
+        let timeseries = Timeseries::default();
+        for unit in &[Unit::Temperature, Unit::Humidity, Unit::Pressure] {
+            let mut surface = render(&timeseries, *unit, TimeFormat::DateTime, None);
+            surface.flush();
+            assert!(
+                surface
+                    .data()
+                    .unwrap()
+                    .iter()
+                    .all(|channel| *channel == 0xff),
+                "Drew something without data for {}",
+                unit.name()
+            );
+        }
+    }
+}
@@ -1,4 +1,5 @@
 mod graph;
 mod graph_painter;
+pub mod print;
 pub use graph::Graph;
-pub use graph_painter::{Data, GraphPainter, Unit};
+pub use graph_painter::{series_color, Data, GraphPainter, ImageFormat};
@@ -0,0 +1,131 @@
+use super::GraphPainter;
+use crate::{data::Statistics, units::Unit};
+use gtk::{cairo, prelude::*};
+use plotters::style::RGBColor;
+
+pub struct Report {
+    pub title: String,
+    /// Key value pairs printed below the title of every page
+    pub metadata: Vec<(String, String)>,
+}
+
+/// Show the print dialog for a report of every unit displayed by `painter`.
+pub fn print(
+    painter: &GraphPainter,
+    parent: Option<&gtk::Window>,
+    report: Report,
+) -> Result<(), glib::Error> {
+    let operation = gtk::PrintOperation::new();
+    // every unit gets its own page
+    operation.set_n_pages(Unit::ALL.len() as i32);
+    operation.set_unit(gtk::Unit::Points);
+    operation.set_job_name(&report.title);
+    operation.set_embed_page_setup(true);
+
+    let painter = painter.clone();
+    operation.connect_draw_page(move |_, ctx, page| {
+        if let Some(&unit) = Unit::ALL.get(page as usize) {
+            if let Err(e) = draw_page(&painter, ctx, &report, unit) {
+                tracing::error!("Failed drawing page {}: {}", page, e);
+            }
+        }
+    });
+
+    operation.run(gtk::PrintOperationAction::PrintDialog, parent)?;
+
+    Ok(())
+}
+
+pub fn statistics_lines(unit: Unit, stats: &Statistics) -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "Minimum",
+            format!(
+                "{} at {}",
+                unit.format_value(stats.min.1),
+                crate::util::format_timestamp(stats.min.0)
+            ),
+        ),
+        (
+            "Maximum",
+            format!(
+                "{} at {}",
+                unit.format_value(stats.max.1),
+                crate::util::format_timestamp(stats.max.0)
+            ),
+        ),
+        ("Mean", unit.format_value(stats.mean)),
+        ("Median", unit.format_value(stats.median)),
+        ("Standard deviation", unit.format_delta(stats.std_dev)),
+        ("Change", unit.format_delta(stats.delta)),
+        ("Samples", stats.count.to_string()),
+    ]
+}
+
+fn draw_page(
+    painter: &GraphPainter,
+    ctx: &gtk::PrintContext,
+    report: &Report,
+    unit: Unit,
+) -> Result<(), anyhow::Error> {
+    const LINE: f64 = 14.;
+    let cr = ctx.cairo_context();
+    let width = ctx.width();
+    let height = ctx.height();
+
+    let text = |y: f64, size: f64, weight: cairo::FontWeight, s: &str| {
+        cr.set_source_rgb(0., 0., 0.);
+        cr.select_font_face("sans-serif", cairo::FontSlant::Normal, weight);
+        cr.set_font_size(size);
+        cr.move_to(0., y);
+        cr.show_text(s)
+    };
+
+    let mut y = 16.;
+    text(y, 16., cairo::FontWeight::Bold, &report.title)?;
+    for (key, value) in &report.metadata {
+        y += LINE;
+        text(
+            y,
+            10.,
+            cairo::FontWeight::Normal,
+            &format!("{}: {}", key, value),
+        )?;
+    }
+
+    y += 2. * LINE;
+    text(y, 14., cairo::FontWeight::Bold, unit.label())?;
+    y += LINE / 2.;
+
+    let graph_height = (height * 0.5).max(100.);
+    cr.save()?;
+    cr.translate(0., y);
+    painter.render_unit(
+        &cr,
+        (width as u32, graph_height as u32),
+        unit,
+        RGBColor(0, 0, 0),
+    )?;
+    cr.restore()?;
+    y += graph_height + LINE;
+
+    match painter.statistics(unit) {
+        Some(stats) => {
+            for (key, value) in statistics_lines(unit, &stats) {
+                y += LINE;
+                text(
+                    y,
+                    10.,
+                    cairo::FontWeight::Normal,
+                    &format!("{}: {}", key, value),
+                )?;
+            }
+        }
+        None => {
+            y += LINE;
+            text(y, 10., cairo::FontWeight::Normal, "No data in range")?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,19 +1,52 @@
+use gtk::prelude::*;
+
+mod alert_history;
+mod calibration_editor;
 pub mod graph;
 mod preferences_window;
+mod sensor_comparison;
 mod sensor_detail;
+mod sensor_group_row;
 mod sensor_overview;
 mod sensor_overview_detail;
+mod sensor_properties_window;
 mod sensor_row;
+mod tendency_indicator;
+mod threshold_editor;
 mod time_date_picker;
 mod validated_entry;
 pub mod window;
 
+pub use alert_history::AlertHistory;
+pub use calibration_editor::CalibrationEditor;
 pub use graph::Graph;
 pub use preferences_window::PreferencesWindow;
+pub use sensor_comparison::SensorComparison;
 pub use sensor_detail::SensorDetail;
+pub use sensor_group_row::SensorGroupRow;
 pub use sensor_overview::SensorOverview;
 pub use sensor_overview_detail::SensorOverviewDetail;
+pub use sensor_properties_window::SensorPropertiesWindow;
 pub use sensor_row::SensorRow;
+pub use tendency_indicator::TendencyIndicator;
+pub use threshold_editor::ThresholdEditor;
 pub use time_date_picker::TimeDatePicker;
 pub use validated_entry::ValidatedEntry;
 pub use window::Window;
+
+/// Tell the user about `error` in a dialog on top of the window `widget` is in.
+pub fn show_error(widget: &impl IsA<gtk::Widget>, message: &str, error: &dyn std::fmt::Display) {
+    let parent = widget
+        .root()
+        .and_then(|root| root.downcast::<gtk::Window>().ok());
+    let dialog = gtk::MessageDialog::new(
+        parent.as_ref(),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Close,
+        message,
+    );
+    dialog.set_secondary_text(Some(&error.to_string()));
+    dialog.connect_response(|dialog, _| dialog.close());
+    dialog.show();
+}
@@ -1,12 +1,19 @@
 use gtk::{prelude::*, subclass::prelude::ObjectSubclassExt};
+use uuid::Uuid;
 
-use crate::widgets::validated_entry::ValidatorResult;
+use crate::{
+    alerts::{self, Rule, RuleKind},
+    sensor_obj::SensorObj,
+    units::{PressureUnit, TemperatureUnit, Unit, UnitSystem},
+    widgets::validated_entry::ValidatorResult,
+};
 mod imp {
     use crate::widgets::validated_entry::ValidatorResult;
 
     use super::*;
     use gtk::{subclass::prelude::*, CompositeTemplate};
     use adw::subclass::prelude::*;
+    use std::cell::RefCell;
 
     #[derive(Debug, CompositeTemplate)]
     #[template(resource = "/li/_5kw/BleWsGtk/preferences_window.ui")]
@@ -15,8 +22,48 @@ mod imp {
         pub dark_theme: TemplateChild<gtk::Switch>,
         #[template_child]
         pub endpoint: TemplateChild<crate::widgets::ValidatedEntry>,
+        #[template_child]
+        pub temperature_unit: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub pressure_unit: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub sea_level_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub raw_values_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub background_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub autostart_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub alert_rule_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub rule_sensor: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub rule_sensor_labels: TemplateChild<gtk::StringList>,
+        #[template_child]
+        pub rule_kind: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub rule_unit_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub rule_unit: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub rule_value_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub rule_value: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub rule_hysteresis_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub rule_hysteresis: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub rule_minutes_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub rule_minutes: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub add_rule_button: TemplateChild<gtk::Button>,
 
         pub settings: gio::Settings,
+        /// id and label of the sensors rules can be created for
+        pub sensors: RefCell<Vec<(Uuid, String)>>,
     }
 
     impl Default for PreferencesWindow {
@@ -24,7 +71,27 @@ mod imp {
             Self {
                 dark_theme: Default::default(),
                 endpoint: Default::default(),
+                temperature_unit: Default::default(),
+                pressure_unit: Default::default(),
+                sea_level_switch: Default::default(),
+                raw_values_switch: Default::default(),
+                background_switch: Default::default(),
+                autostart_switch: Default::default(),
+                alert_rule_list: Default::default(),
+                rule_sensor: Default::default(),
+                rule_sensor_labels: Default::default(),
+                rule_kind: Default::default(),
+                rule_unit_row: Default::default(),
+                rule_unit: Default::default(),
+                rule_value_row: Default::default(),
+                rule_value: Default::default(),
+                rule_hysteresis_row: Default::default(),
+                rule_hysteresis: Default::default(),
+                rule_minutes_row: Default::default(),
+                rule_minutes: Default::default(),
+                add_rule_button: Default::default(),
                 settings: crate::config::settings(),
+                sensors: Default::default(),
             }
         }
     }
@@ -50,6 +117,18 @@ mod imp {
             self.settings
                 .bind("dark-theme", &*self.dark_theme, "state")
                 .build();
+            self.settings
+                .bind("sea-level-pressure", &*self.sea_level_switch, "active")
+                .build();
+            self.settings
+                .bind("raw-values", &*self.raw_values_switch, "active")
+                .build();
+            self.settings
+                .bind("run-in-background", &*self.background_switch, "active")
+                .build();
+            self.settings
+                .bind("autostart", &*self.autostart_switch, "active")
+                .build();
             let endpoint = self.settings.get::<String>("endpoint");
             if let ValidatorResult::Error(_) = validate_endpoint(&endpoint) {
                 self.settings.set("endpoint", &"").unwrap();
@@ -65,6 +144,65 @@ mod imp {
                     self_.settings.set("endpoint", &text).unwrap();
                 }));
             self.endpoint.set_validator(validate_endpoint);
+
+            let units = UnitSystem::from_settings(&self.settings);
+            let temperature = TemperatureUnit::ALL
+                .iter()
+                .position(|unit| *unit == units.temperature)
+                .unwrap();
+            self.temperature_unit.set_selected(temperature as u32);
+            let pressure = PressureUnit::ALL
+                .iter()
+                .position(|unit| *unit == units.pressure)
+                .unwrap();
+            self.pressure_unit.set_selected(pressure as u32);
+            self.temperature_unit.connect_selected_notify(
+                glib::clone!(@weak obj => move |dropdown| {
+                    let self_ = PreferencesWindow::from_instance(&obj);
+                    if let Some(unit) = TemperatureUnit::ALL.get(dropdown.selected() as usize) {
+                        self_.settings.set_string("temperature-unit", unit.nick()).unwrap();
+                    }
+                }),
+            );
+            self.pressure_unit
+                .connect_selected_notify(glib::clone!(@weak obj => move |dropdown| {
+                    let self_ = PreferencesWindow::from_instance(&obj);
+                    if let Some(unit) = PressureUnit::ALL.get(dropdown.selected() as usize) {
+                        self_.settings.set_string("pressure-unit", unit.nick()).unwrap();
+                    }
+                }));
+            for key in &["temperature-unit", "pressure-unit"] {
+                self.settings.connect_changed(
+                    Some(key),
+                    glib::clone!(@weak obj => move |settings, _| {
+                        // the event loop might only see the change after us
+                        UnitSystem::from_settings(settings).set_current();
+                        obj.update_rule_rows();
+                        obj.refresh_rules();
+                    }),
+                );
+            }
+
+            self.rule_kind
+                .connect_selected_notify(glib::clone!(@weak obj => move |_| {
+                    obj.update_rule_rows();
+                }));
+            self.rule_unit
+                .connect_selected_notify(glib::clone!(@weak obj => move |_| {
+                    obj.update_rule_rows();
+                }));
+            obj.update_rule_rows();
+            self.add_rule_button
+                .connect_clicked(glib::clone!(@weak obj => move |_| {
+                    obj.add_rule();
+                }));
+            self.settings.connect_changed(
+                Some("alert-rules"),
+                glib::clone!(@weak obj => move |_, _| {
+                    obj.refresh_rules();
+                }),
+            );
+            obj.refresh_rules();
         }
     }
 
@@ -84,6 +222,143 @@ impl PreferencesWindow {
         let ret: Self = glib::Object::new(&[]).unwrap();
         ret
     }
+
+    /// Sensors that can be picked when creating an alert rule.
+    pub fn set_sensors(&self, sensors: &gio::ListModel) {
+        let self_ = imp::PreferencesWindow::from_instance(self);
+        let sensors = (0..sensors.n_items())
+            .filter_map(|i| sensors.item(i)?.downcast::<SensorObj>().ok())
+            .map(|sensor| {
+                let label = sensor
+                    .data()
+                    .label
+                    .borrow()
+                    .clone()
+                    .unwrap_or_else(|| sensor.id().to_string());
+                (sensor.id(), label)
+            })
+            .collect::<Vec<_>>();
+        let labels = sensors
+            .iter()
+            .map(|(_, label)| label.as_str())
+            .collect::<Vec<_>>();
+        self_
+            .rule_sensor_labels
+            .splice(0, self_.rule_sensor_labels.n_items(), &labels);
+        self_.sensors.replace(sensors);
+        self.refresh_rules();
+    }
+
+    fn sensor_label(&self, id: Uuid) -> String {
+        let self_ = imp::PreferencesWindow::from_instance(self);
+        self_
+            .sensors
+            .borrow()
+            .iter()
+            .find(|(sensor, _)| *sensor == id)
+            .map(|(_, label)| label.clone())
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    fn rule_unit(&self) -> Unit {
+        let self_ = imp::PreferencesWindow::from_instance(self);
+        Unit::ALL
+            .get(self_.rule_unit.selected() as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Only show the inputs the selected kind of rule uses.
+    fn update_rule_rows(&self) {
+        let self_ = imp::PreferencesWindow::from_instance(self);
+        let kind = self_.rule_kind.selected();
+        self_.rule_unit_row.set_visible(kind != 3);
+        self_.rule_value_row.set_visible(kind != 3);
+        self_.rule_hysteresis_row.set_visible(kind < 2);
+        self_.rule_minutes_row.set_visible(kind >= 2);
+        let symbol = UnitSystem::current().symbol(self.rule_unit());
+        self_
+            .rule_value_row
+            .set_subtitle(Some(&format!("Limit or change in {}", symbol)));
+    }
+
+    fn new_rule(&self) -> Option<Rule> {
+        let self_ = imp::PreferencesWindow::from_instance(self);
+        let sensor = self_
+            .sensors
+            .borrow()
+            .get(self_.rule_sensor.selected() as usize)?
+            .0;
+        let unit = self.rule_unit();
+        let units = UnitSystem::current();
+        let value = self_.rule_value.value();
+        let hysteresis = units.to_raw_delta(unit, self_.rule_hysteresis.value());
+        let minutes = self_.rule_minutes.value_as_int() as u32;
+        let kind = match self_.rule_kind.selected() {
+            0 => RuleKind::Above {
+                unit,
+                limit: units.to_raw(unit, value),
+                hysteresis,
+            },
+            1 => RuleKind::Below {
+                unit,
+                limit: units.to_raw(unit, value),
+                hysteresis,
+            },
+            2 => RuleKind::RateOfChange {
+                unit,
+                change: units.to_raw_delta(unit, value.abs()),
+                minutes,
+            },
+            _ => RuleKind::Disconnected { minutes },
+        };
+        Some(Rule { sensor, kind })
+    }
+
+    fn add_rule(&self) {
+        let self_ = imp::PreferencesWindow::from_instance(self);
+        if let Some(rule) = self.new_rule() {
+            let mut rules = alerts::load_rules(&self_.settings);
+            rules.push(rule);
+            if let Err(e) = alerts::save_rules(&self_.settings, &rules) {
+                tracing::error!("Failed saving alert rules: {}", e);
+            }
+        }
+    }
+
+    fn remove_rule(&self, i: usize) {
+        let self_ = imp::PreferencesWindow::from_instance(self);
+        let mut rules = alerts::load_rules(&self_.settings);
+        if i < rules.len() {
+            rules.remove(i);
+            if let Err(e) = alerts::save_rules(&self_.settings, &rules) {
+                tracing::error!("Failed saving alert rules: {}", e);
+            }
+        }
+    }
+
+    fn refresh_rules(&self) {
+        let self_ = imp::PreferencesWindow::from_instance(self);
+        while let Some(child) = self_.alert_rule_list.first_child() {
+            self_.alert_rule_list.remove(&child);
+        }
+
+        let rules = alerts::load_rules(&self_.settings);
+        self_.alert_rule_list.set_visible(!rules.is_empty());
+        for (i, rule) in rules.iter().enumerate() {
+            let row = adw::ActionRow::new();
+            row.set_title(Some(&self.sensor_label(rule.sensor)));
+            row.set_subtitle(Some(&rule.kind.describe()));
+            let remove = gtk::Button::from_icon_name(Some("user-trash-symbolic"));
+            remove.set_valign(gtk::Align::Center);
+            remove.set_tooltip_text(Some("Remove rule"));
+            remove.connect_clicked(glib::clone!(@weak self as this => move |_| {
+                this.remove_rule(i);
+            }));
+            row.add_suffix(&remove);
+            self_.alert_rule_list.append(&row);
+        }
+    }
 }
 
 fn validate_endpoint(s: &str) -> ValidatorResult {
@@ -0,0 +1,128 @@
+use crate::{
+    sensor_obj::SensorObj,
+    units::Unit,
+    widgets::graph::{self, Graph, GraphPainter},
+};
+use glib::subclass::prelude::*;
+use gtk::{prelude::*, CompositeTemplate};
+
+mod imp {
+    use super::*;
+    use gtk::subclass::prelude::*;
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/li/_5kw/BleWsGtk/sensor_comparison.ui")]
+    pub struct SensorComparison {
+        #[template_child]
+        pub unit_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub sensor_list: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub graph: TemplateChild<Graph>,
+
+        pub painter: GraphPainter,
+        pub sensors: RefCell<Vec<(SensorObj, gtk::CheckButton)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SensorComparison {
+        const NAME: &'static str = "BleWsSensorComparison";
+        type Type = super::SensorComparison;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for SensorComparison {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            self.graph.set_painter(&self.painter);
+
+            self.unit_dropdown
+                .connect_selected_notify(glib::clone!(@weak obj => move |dropdown| {
+                    let self_ = Self::from_instance(&obj);
+                    let unit = Unit::ALL.get(dropdown.selected() as usize);
+                    self_.painter.set_displayed_unit(unit.copied().unwrap_or_default());
+                }));
+        }
+    }
+
+    impl BoxImpl for SensorComparison {}
+    impl WidgetImpl for SensorComparison {}
+}
+
+glib::wrapper! {
+    pub struct SensorComparison(ObjectSubclass<imp::SensorComparison>) @extends gtk::Box, gtk::Widget;
+}
+
+impl SensorComparison {
+    /// Replace the sensors that can be compared, all of them are shown initially.
+    pub fn set_sensors(&self, sensors: &[SensorObj]) {
+        let self_ = imp::SensorComparison::from_instance(self);
+        while let Some(child) = self_.sensor_list.first_child() {
+            self_.sensor_list.remove(&child);
+        }
+
+        let mut entries = self_.sensors.borrow_mut();
+        entries.clear();
+        for (i, sensor) in sensors.iter().enumerate() {
+            let color = graph::series_color(i);
+            let label = sensor
+                .data()
+                .label
+                .borrow()
+                .clone()
+                .unwrap_or_else(|| sensor.id().to_string());
+
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+            let check = gtk::CheckButton::new();
+            check.set_active(true);
+            check.connect_toggled(glib::clone!(@weak self as this => move |_| {
+                this.refresh_painter();
+            }));
+            let legend = gtk::Label::new(None);
+            legend.set_markup(&format!(
+                "<span foreground=\"#{:02x}{:02x}{:02x}\">●</span> {}",
+                color.0,
+                color.1,
+                color.2,
+                glib::markup_escape_text(&label)
+            ));
+            row.append(&check);
+            row.append(&legend);
+            self_.sensor_list.append(&row);
+
+            entries.push((sensor.clone(), check));
+        }
+        drop(entries);
+
+        self.refresh_painter();
+    }
+
+    pub fn refresh_units(&self) {
+        let self_ = imp::SensorComparison::from_instance(self);
+        self_.painter.invalidate_contents();
+    }
+
+    fn refresh_painter(&self) {
+        let self_ = imp::SensorComparison::from_instance(self);
+        let shown = self_
+            .sensors
+            .borrow()
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, check))| check.is_active())
+            .map(|(i, (sensor, _))| (sensor.clone(), graph::series_color(i)))
+            .collect::<Vec<_>>();
+        self_
+            .painter
+            .set_displayed_data(Some(graph::Data::Comparison(shown)));
+    }
+}
@@ -1,24 +1,34 @@
 use std::time::Duration;
 
 use crate::{
-    data::Timeseries,
+    data::{Corrections, Timeseries},
     event_loop::Event,
+    local_store::SensorSettings,
     sensor_obj::SensorObj,
-    widgets::graph::{Graph, GraphPainter, Unit},
+    units::{Unit, UnitSystem},
+    widgets::graph::{print, Graph, GraphPainter, ImageFormat},
 };
+use adw::prelude::*;
+use ble_ws_api::data::Timestamp;
 use glib::subclass::prelude::*;
 use gtk::{prelude::*, CompositeTemplate};
 
 use super::graph::Data;
 
+/// Seconds between refetching the overlay of the live range.
+const OVERLAY_REFRESH_SECS: u32 = 60;
+
+/// Minimum time between recomputing the statistics, live data and
+/// dragging the range invalidate the graph much more often.
+const STATISTICS_DELAY: Duration = Duration::from_millis(250);
+
 mod imp {
 
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
 
     use crate::event_loop::Event;
 
     use super::*;
-    use ble_ws_api::data::Timestamp;
     use gtk::subclass::prelude::*;
     use once_cell::unsync::OnceCell;
 
@@ -33,6 +43,14 @@ mod imp {
         #[template_child]
         pub graph_pressure: TemplateChild<Graph>,
         #[template_child]
+        pub graph_dew_point: TemplateChild<Graph>,
+        #[template_child]
+        pub graph_absolute_humidity: TemplateChild<Graph>,
+        #[template_child]
+        pub graph_heat_index: TemplateChild<Graph>,
+        #[template_child]
+        pub graph_vapour_pressure_deficit: TemplateChild<Graph>,
+        #[template_child]
         pub from_picker: TemplateChild<crate::widgets::TimeDatePicker>,
         #[template_child]
         pub to_picker: TemplateChild<crate::widgets::TimeDatePicker>,
@@ -48,11 +66,53 @@ mod imp {
         pub advanced_menu: TemplateChild<gtk::Box>,
         #[template_child]
         pub live_slider_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub overlay_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub stat_min_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub stat_min: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub stat_max_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub stat_max: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub stat_mean: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub stat_median: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub stat_std_dev: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub stat_delta: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub stat_count: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub threshold_editor: TemplateChild<crate::widgets::ThresholdEditor>,
+        #[template_child]
+        pub calibration_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub calibration_editor: TemplateChild<crate::widgets::CalibrationEditor>,
+        #[template_child]
+        pub altitude: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub reference_temperature_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub reference_temperature_enabled: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub reference_temperature: TemplateChild<gtk::SpinButton>,
 
         pub evt_tx: OnceCell<glib::Sender<Event>>,
         pub change_tx: OnceCell<tokio::sync::mpsc::Sender<()>>,
         pub painter: GraphPainter,
         pub sensor: RefCell<Option<SensorObj>>,
+        pub sensor_bindings: RefCell<Vec<glib::Binding>>,
+        pub export_dialog: RefCell<Option<gtk::FileChooserNative>>,
+        /// set while the location gets filled programmatically
+        pub updating_location: Cell<bool>,
+        /// set while [`super::SensorDetail::show_range`] fills the pickers
+        pub updating_range: Cell<bool>,
+        /// refresh of the statistics that's waiting for [`super::STATISTICS_DELAY`]
+        pub pending_statistics: RefCell<Option<glib::SourceId>>,
     }
 
     #[glib::object_subclass]
@@ -63,6 +123,15 @@ mod imp {
 
         fn class_init(klass: &mut Self::Class) {
             Self::bind_template(klass);
+            klass.install_action("detail.export-image", None, |obj, _, _| {
+                obj.open_export_dialog();
+            });
+            klass.install_action("detail.print", None, |obj, _, _| {
+                obj.print();
+            });
+            klass.install_action("detail.properties", None, |obj, _, _| {
+                obj.open_properties();
+            });
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -79,21 +148,29 @@ mod imp {
                 .set_datetime(now.replace_date(now.date().previous_day().unwrap()));
             self.to_picker.set_datetime(now);
 
+            // every change of unit, bounds or data invalidates the painter
+            self.painter
+                .connect_invalidate_contents(glib::clone!(@weak obj => move |_| {
+                    obj.refresh_statistics_later();
+                }));
+
             self.graph_humidity.set_painter(&self.painter);
             self.graph_temperature.set_painter(&self.painter);
             self.graph_pressure.set_painter(&self.painter);
+            self.graph_dew_point.set_painter(&self.painter);
+            self.graph_absolute_humidity.set_painter(&self.painter);
+            self.graph_heat_index.set_painter(&self.painter);
+            self.graph_vapour_pressure_deficit
+                .set_painter(&self.painter);
             let painter = self.painter.downgrade();
             self.detail_stack
                 .connect_visible_child_name_notify(move |me| {
                     let name = me
                         .visible_child_name()
                         .expect("Visible child name changed to something without a name");
-                    let unit = match name.as_str() {
-                        "temperature" => Unit::Temperature,
-                        "pressure" => Unit::Pressure,
-                        "humidity" => Unit::Humidity,
-                        _ => panic!("Invalid child name"),
-                    };
+                    let unit = name
+                        .parse::<Unit>()
+                        .unwrap_or_else(|_| panic!("Invalid child name"));
 
                     if let Some(painter) = painter.upgrade() {
                         if painter.displayed_unit() != unit {
@@ -151,6 +228,15 @@ mod imp {
                         }
                         self_.menu_stack.set_visible_child(&*self_.advanced_menu);
                     }
+                    self_.painter.set_overlay(None, "");
+                    obj.request_overlay();
+                }));
+
+            self.overlay_dropdown
+                .connect_selected_notify(glib::clone!(@weak obj => move |_| {
+                    let self_ = Self::from_instance(&obj);
+                    self_.painter.set_overlay(None, "");
+                    obj.request_overlay();
                 }));
         }
     }
@@ -168,19 +254,28 @@ impl SensorDetail {
         let self_ = imp::SensorDetail::from_instance(&self);
         let ctx = glib::MainContext::default();
         let (change_tx, mut change_rx) = tokio::sync::mpsc::channel(1);
+        // the receiver runs on this thread, so never wait for room in the
+        // channel, a full one already has a pending change
+        let range_changed = glib::clone!(@weak self as this, @strong change_tx => move || {
+            let self_ = imp::SensorDetail::from_instance(&this);
+            if !self_.updating_range.get() {
+                let _ = change_tx.try_send(());
+            }
+        });
         self_.from_picker.connect_notify_local(
             Some("datetime"),
-            glib::clone!(@strong change_tx => move |_, _| {
-                change_tx.blocking_send(()).unwrap();
-            }),
+            glib::clone!(@strong range_changed => move |_, _| range_changed()),
         );
+        self_
+            .to_picker
+            .connect_notify_local(Some("datetime"), move |_, _| range_changed());
 
-        self_.to_picker.connect_notify_local(
-            Some("datetime"),
-            glib::clone!(@strong change_tx => move |_, _| {
-                change_tx.blocking_send(()).unwrap();
-            }),
-        );
+        // the live range moves with the slider
+        self_
+            .live_slider
+            .connect_value_changed(glib::clone!(@strong change_tx => move |_| {
+                let _ = change_tx.try_send(());
+            }));
 
         let this = self.clone();
         ctx.spawn_local({
@@ -195,7 +290,15 @@ impl SensorDetail {
                                 timeout = glib::timeout_future(Duration::from_millis(500));
                             }
                             () = &mut timeout => {
-                                if let Some(evt) = this.mk_details_range() {
+                                let live = imp::SensorDetail::from_instance(&this)
+                                    .live_switch
+                                    .is_active();
+                                if !live {
+                                    if let Some(evt) = this.mk_details_range() {
+                                        tx.send(evt).unwrap();
+                                    }
+                                }
+                                if let Some(evt) = this.mk_overlay_range() {
                                     tx.send(evt).unwrap();
                                 }
                                 break;
@@ -206,10 +309,117 @@ impl SensorDetail {
             }
         });
 
+        self_.threshold_editor.connect_changed(
+            glib::clone!(@weak self as this, @strong tx => move |thresholds| {
+                let self_ = imp::SensorDetail::from_instance(&this);
+                if let Some(sensor) = &*self_.sensor.borrow() {
+                    tx.send(Event::ChangeThresholds {
+                        id: sensor.id(),
+                        thresholds,
+                    })
+                    .unwrap();
+                }
+            }),
+        );
+
+        self_.calibration_editor.connect_changed(
+            glib::clone!(@weak self as this, @strong tx => move |calibration| {
+                let self_ = imp::SensorDetail::from_instance(&this);
+                if let Some(sensor) = &*self_.sensor.borrow() {
+                    tx.send(Event::ChangeCalibration {
+                        id: sensor.id(),
+                        calibration,
+                    })
+                    .unwrap();
+                }
+            }),
+        );
+
+        let location_changed = glib::clone!(@weak self as this, @strong tx => move || {
+            if let Some(evt) = this.mk_location_change() {
+                tx.send(evt).unwrap();
+            }
+        });
+        self_.altitude.connect_value_changed(
+            glib::clone!(@strong location_changed => move |_| location_changed()),
+        );
+        self_.reference_temperature.connect_value_changed(
+            glib::clone!(@strong location_changed => move |_| location_changed()),
+        );
+        self_
+            .reference_temperature_enabled
+            .connect_toggled(move |_| location_changed());
+        self.set_location_units(UnitSystem::current());
+
+        // the live range moves along with the time, so does its overlay
+        glib::timeout_add_seconds_local(
+            OVERLAY_REFRESH_SECS,
+            glib::clone!(@weak self as this => @default-return glib::Continue(false), move || {
+                let self_ = imp::SensorDetail::from_instance(&this);
+                if self_.live_switch.is_active() {
+                    this.request_overlay();
+                }
+                glib::Continue(true)
+            }),
+        );
+
         self_.change_tx.set(change_tx).unwrap();
         self_.evt_tx.set(tx).unwrap();
     }
 
+    fn mk_location_change(&self) -> Option<Event> {
+        let self_ = imp::SensorDetail::from_instance(self);
+        if self_.updating_location.get() {
+            return None;
+        }
+        let id = self_.sensor.borrow().as_ref()?.id();
+        let reference_temperature = if self_.reference_temperature_enabled.is_active() {
+            let raw = UnitSystem::current()
+                .to_raw(Unit::Temperature, self_.reference_temperature.value());
+            Some(raw / Unit::Temperature.scale())
+        } else {
+            None
+        };
+        Some(Event::ChangeLocation {
+            id,
+            altitude: self_.altitude.value(),
+            reference_temperature,
+        })
+    }
+
+    /// Display the location in `settings` without emitting a change.
+    fn set_location(&self, settings: &SensorSettings) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        self_.updating_location.set(true);
+        self_.altitude.set_value(settings.altitude);
+        self_
+            .reference_temperature_enabled
+            .set_active(settings.reference_temperature.is_some());
+        if let Some(celsius) = settings.reference_temperature {
+            let raw = celsius * Unit::Temperature.scale();
+            self_
+                .reference_temperature
+                .set_value(UnitSystem::current().convert(Unit::Temperature, raw));
+        }
+        self_.updating_location.set(false);
+    }
+
+    /// Enter the reference temperature in `units`.
+    fn set_location_units(&self, units: UnitSystem) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        self_.updating_location.set(true);
+        self_.reference_temperature_label.set_label(&format!(
+            "Reference temperature ({})",
+            units.symbol(Unit::Temperature)
+        ));
+        let adjustment = self_.reference_temperature.adjustment();
+        adjustment.set_lower(units.convert(Unit::Temperature, -4000.));
+        adjustment.set_upper(units.convert(Unit::Temperature, 5000.));
+        adjustment.set_step_increment(units.convert_delta(Unit::Temperature, 50.));
+        adjustment.set_page_increment(units.convert_delta(Unit::Temperature, 500.));
+        self_.updating_location.set(false);
+    }
+
     fn mk_details_range(&self) -> Option<Event> {
         let self_ = imp::SensorDetail::from_instance(self);
 
@@ -232,6 +442,279 @@ impl SensorDetail {
         }
     }
 
+    fn overlay_shift(&self) -> Option<(u32, &'static str)> {
+        let self_ = imp::SensorDetail::from_instance(self);
+        match self_.overlay_dropdown.selected() {
+            1 => Some((Timestamp::ONE_DAY.as_u32(), "Previous day")),
+            2 => Some((7 * Timestamp::ONE_DAY.as_u32(), "Previous week")),
+            _ => None,
+        }
+    }
+
+    /// Creates the request for the overlay of the displayed range, if any
+    /// is selected. The current overlay stays until the new one arrives.
+    fn mk_overlay_range(&self) -> Option<Event> {
+        let self_ = imp::SensorDetail::from_instance(self);
+        let (shift, _) = self.overlay_shift()?;
+        let id = self_.sensor.borrow().as_ref()?.id();
+        let (from, to) = self.displayed_range();
+
+        if from > to {
+            None
+        } else {
+            Some(Event::DetailOverlayChanged {
+                id,
+                from,
+                to,
+                shift,
+            })
+        }
+    }
+
+    fn request_overlay(&self) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        if let (Some(tx), Some(evt)) = (self_.evt_tx.get(), self.mk_overlay_range()) {
+            tx.send(evt).unwrap();
+        }
+    }
+
+    pub fn set_overlay_timeseries(&self, timeseries: Timeseries, shift: u32) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        // the selection might have changed while the request was in flight
+        if let Some((selected_shift, label)) = self.overlay_shift() {
+            if selected_shift == shift {
+                self_.painter.set_overlay(Some((timeseries, shift)), label);
+            }
+        }
+    }
+
+    /// Refresh the statistics after [`STATISTICS_DELAY`], unless a refresh
+    /// is pending already.
+    fn refresh_statistics_later(&self) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        if self_.pending_statistics.borrow().is_some() {
+            return;
+        }
+        let source = glib::timeout_add_local(
+            STATISTICS_DELAY,
+            glib::clone!(@weak self as this => @default-return glib::Continue(false), move || {
+                let self_ = imp::SensorDetail::from_instance(&this);
+                // the source is done once this returns, it must not be removed anymore
+                self_.pending_statistics.replace(None);
+                this.refresh_statistics();
+                glib::Continue(false)
+            }),
+        );
+        self_.pending_statistics.replace(Some(source));
+    }
+
+    fn refresh_statistics(&self) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        let unit = self_.painter.displayed_unit();
+        match self_.painter.statistics(unit) {
+            Some(stats) => {
+                self_.stat_min.set_label(&unit.format_value(stats.min.1));
+                self_
+                    .stat_min_row
+                    .set_subtitle(Some(&crate::util::format_timestamp(stats.min.0)));
+                self_.stat_max.set_label(&unit.format_value(stats.max.1));
+                self_
+                    .stat_max_row
+                    .set_subtitle(Some(&crate::util::format_timestamp(stats.max.0)));
+                self_.stat_mean.set_label(&unit.format_value(stats.mean));
+                self_
+                    .stat_median
+                    .set_label(&unit.format_value(stats.median));
+                self_
+                    .stat_std_dev
+                    .set_label(&unit.format_delta(stats.std_dev));
+                self_.stat_delta.set_label(&unit.format_delta(stats.delta));
+                self_.stat_count.set_label(&stats.count.to_string());
+            }
+            None => {
+                for label in &[
+                    &self_.stat_min,
+                    &self_.stat_max,
+                    &self_.stat_mean,
+                    &self_.stat_median,
+                    &self_.stat_std_dev,
+                    &self_.stat_delta,
+                ] {
+                    label.set_label("–");
+                }
+                self_.stat_count.set_label("0");
+                self_.stat_min_row.set_subtitle(None);
+                self_.stat_max_row.set_subtitle(None);
+            }
+        }
+    }
+
+    /// Range that's currently displayed by the graph.
+    fn displayed_range(&self) -> (time::OffsetDateTime, time::OffsetDateTime) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        if self_.live_switch.is_active() {
+            let now = crate::util::now_local();
+            let minutes = self_.live_slider.value().floor() as i64;
+            (now - time::Duration::minutes(minutes), now)
+        } else {
+            (self_.from_picker.datetime(), self_.to_picker.datetime())
+        }
+    }
+
+    fn sensor_label(&self) -> String {
+        let self_ = imp::SensorDetail::from_instance(self);
+        let sensor = self_.sensor.borrow();
+        sensor
+            .as_ref()
+            .map(|sensor| {
+                sensor
+                    .data()
+                    .label
+                    .borrow()
+                    .clone()
+                    .unwrap_or_else(|| sensor.id().to_string())
+            })
+            .unwrap_or_default()
+    }
+
+    fn export_title(&self) -> String {
+        let self_ = imp::SensorDetail::from_instance(self);
+        let label = self.sensor_label();
+        let (from, to) = self.displayed_range();
+        format!(
+            "{} – {} – {} to {}",
+            label,
+            self_.painter.displayed_unit().label(),
+            crate::util::format_timestamp(from.unix_timestamp() as u32),
+            crate::util::format_timestamp(to.unix_timestamp() as u32),
+        )
+    }
+
+    fn open_properties(&self) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        if let (Some(sensor), Some(tx)) = (&*self_.sensor.borrow(), self_.evt_tx.get()) {
+            crate::widgets::SensorPropertiesWindow::open(sensor, tx.clone(), self);
+        }
+    }
+
+    fn open_export_dialog(&self) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        let window = self
+            .root()
+            .and_then(|root| root.downcast::<gtk::Window>().ok());
+        let dialog = gtk::FileChooserNative::new(
+            Some("Export image"),
+            window.as_ref(),
+            gtk::FileChooserAction::Save,
+            Some("_Export"),
+            Some("_Cancel"),
+        );
+        let filters = ImageFormat::ALL
+            .iter()
+            .map(|format| {
+                let filter = gtk::FileFilter::new();
+                filter.set_name(Some(format.label()));
+                filter.add_pattern(&format!("*.{}", format.extension()));
+                dialog.add_filter(&filter);
+                (filter, *format)
+            })
+            .collect::<Vec<_>>();
+        dialog.add_choice(
+            "size",
+            "Size",
+            &["1280x720", "1920x1080", "3840x2160"],
+            &["1280 × 720", "1920 × 1080", "3840 × 2160"],
+        );
+        dialog.set_choice("size", "1920x1080");
+        dialog.set_current_name(&format!("{}.png", self_.painter.displayed_unit().name()));
+
+        dialog.connect_response(glib::clone!(@weak self as this => move |dialog, response| {
+            if response == gtk::ResponseType::Accept {
+                if let Some(mut path) = dialog.file().and_then(|file| file.path()) {
+                    let size = dialog
+                        .choice("size")
+                        .and_then(|size| parse_size(&size))
+                        .unwrap_or((1920, 1080));
+                    // the picked filter decides the format, the extension follows it
+                    let format = dialog
+                        .filter()
+                        .and_then(|picked| {
+                            filters
+                                .iter()
+                                .find(|(filter, _)| *filter == picked)
+                                .map(|(_, format)| *format)
+                        })
+                        .or_else(|| ImageFormat::from_path(&path))
+                        .unwrap_or(ImageFormat::Png);
+                    if ImageFormat::from_path(&path) != Some(format) {
+                        let mut name = path.into_os_string();
+                        name.push(".");
+                        name.push(format.extension());
+                        path = name.into();
+                    }
+                    if let Err(e) = this.export_image(&path, format, size) {
+                        tracing::error!("Failed exporting image: {}", e);
+                        crate::widgets::show_error(&this, "Could not export the image", &e);
+                    }
+                }
+            }
+            let self_ = imp::SensorDetail::from_instance(&this);
+            self_.export_dialog.replace(None);
+        }));
+
+        dialog.show();
+        // the native dialog gets destroyed when the last reference is dropped
+        self_.export_dialog.replace(Some(dialog));
+    }
+
+    pub fn export_image(
+        &self,
+        path: &std::path::Path,
+        format: ImageFormat,
+        size: (u32, u32),
+    ) -> Result<(), anyhow::Error> {
+        let self_ = imp::SensorDetail::from_instance(self);
+        self_
+            .painter
+            .export_image(path, format, size, &self.export_title())
+    }
+
+    fn print(&self) {
+        let self_ = imp::SensorDetail::from_instance(self);
+        let (from, to) = self.displayed_range();
+        let id = self_
+            .sensor
+            .borrow()
+            .as_ref()
+            .map(|sensor| sensor.id().to_string())
+            .unwrap_or_default();
+        let report = print::Report {
+            title: self.sensor_label(),
+            metadata: vec![
+                ("Sensor id".to_owned(), id),
+                (
+                    "Range".to_owned(),
+                    format!(
+                        "{} to {}",
+                        crate::util::format_timestamp(from.unix_timestamp() as u32),
+                        crate::util::format_timestamp(to.unix_timestamp() as u32)
+                    ),
+                ),
+                (
+                    "Printed".to_owned(),
+                    crate::util::format_timestamp(crate::util::now_local().unix_timestamp() as u32),
+                ),
+            ],
+        };
+        let window = self
+            .root()
+            .and_then(|root| root.downcast::<gtk::Window>().ok());
+        if let Err(e) = print::print(&self_.painter, window.as_ref(), report) {
+            tracing::error!("Failed printing: {}", e);
+            crate::widgets::show_error(self, "Could not print the graphs", &e);
+        }
+    }
+
     pub fn stack(&self) -> &gtk::Stack {
         let self_ = imp::SensorDetail::from_instance(&self);
         &self_.detail_stack
@@ -239,7 +722,40 @@ impl SensorDetail {
 
     pub fn set_sensor(&self, sensor: Option<SensorObj>) {
         let self_ = imp::SensorDetail::from_instance(&self);
+        for binding in self_.sensor_bindings.borrow_mut().drain(..) {
+            binding.unbind();
+        }
+        if let Some(sensor) = &sensor {
+            let settings = sensor.settings();
+            self_.threshold_editor.set_thresholds(settings.thresholds);
+            self_
+                .calibration_editor
+                .set_calibrations(settings.calibration);
+            self.set_location(&settings);
+
+            let mut bindings = self_.sensor_bindings.borrow_mut();
+            let binding = sensor
+                .bind_property("corrections", &*self_.calibration_label, "label")
+                .transform_to(|_, val| {
+                    let val = val.get::<Corrections>().unwrap();
+                    Some(val.calibration_label().unwrap_or_default().to_value())
+                })
+                .build();
+            bindings.extend(binding);
+            let binding = sensor
+                .bind_property("corrections", &*self_.calibration_label, "visible")
+                .transform_to(|_, val| {
+                    let val = val.get::<Corrections>().unwrap();
+                    Some(val.calibration_label().is_some().to_value())
+                })
+                .build();
+            bindings.extend(binding);
+            sensor.notify("corrections");
+        }
+        self_.painter.track_settings(sensor.as_ref());
         self_.sensor.replace(sensor);
+        self_.overlay_dropdown.set_selected(0);
+        self_.painter.set_overlay(None, "");
     }
 
     pub fn set_static_timeseries(&self, timeseries: Timeseries) {
@@ -249,8 +765,49 @@ impl SensorDetail {
             .set_displayed_data(Some(Data::Static(timeseries)));
     }
 
+    pub fn set_unit(&self, unit: Unit) {
+        let self_ = imp::SensorDetail::from_instance(&self);
+        self_.detail_stack.set_visible_child_name(unit.name());
+    }
+
+    /// Show the static range `from..to` instead of live data.
+    pub fn show_range(&self, from: time::OffsetDateTime, to: time::OffsetDateTime) {
+        let self_ = imp::SensorDetail::from_instance(&self);
+        self_.updating_range.set(true);
+        self_.from_picker.set_datetime(from);
+        self_.to_picker.set_datetime(to);
+        self_.updating_range.set(false);
+        if self_.live_switch.is_active() {
+            // turning live off requests the range of the pickers
+            self_.live_switch.set_active(false);
+        } else {
+            if let (Some(tx), Some(evt)) = (self_.evt_tx.get(), self.mk_details_range()) {
+                tx.send(evt).unwrap();
+            }
+            self.request_overlay();
+        }
+    }
+
     pub fn set_live(&self, live: bool) {
         let self_ = imp::SensorDetail::from_instance(&self);
         self_.live_switch.set_active(live);
     }
+
+    /// Redisplay everything after the units changed.
+    pub fn refresh_units(&self) {
+        let self_ = imp::SensorDetail::from_instance(&self);
+        self_.threshold_editor.set_units(UnitSystem::current());
+        self_.calibration_editor.set_units(UnitSystem::current());
+        self.set_location_units(UnitSystem::current());
+        if let Some(sensor) = &*self_.sensor.borrow() {
+            self.set_location(&sensor.settings());
+        }
+        // also refreshes the statistics
+        self_.painter.invalidate_contents();
+    }
+}
+
+fn parse_size(s: &str) -> Option<(u32, u32)> {
+    let (width, height) = s.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
 }
@@ -0,0 +1,71 @@
+use crate::sensor_group::SensorGroup;
+use glib::subclass::prelude::*;
+use gtk::{prelude::*, CompositeTemplate};
+
+mod imp {
+    use std::cell::RefCell;
+
+    use super::*;
+    use adw::subclass::prelude::BinImpl;
+    use gtk::subclass::prelude::*;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/li/_5kw/BleWsGtk/sensor_group_row.ui")]
+    pub struct SensorGroupRow {
+        #[template_child]
+        pub title: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub summary: TemplateChild<gtk::Label>,
+
+        pub bindings: RefCell<Vec<glib::Binding>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SensorGroupRow {
+        const NAME: &'static str = "BleWsSensorGroupRow";
+        type Type = super::SensorGroupRow;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for SensorGroupRow {}
+    impl WidgetImpl for SensorGroupRow {}
+    impl BinImpl for SensorGroupRow {}
+}
+
+glib::wrapper! {
+    pub struct SensorGroupRow(ObjectSubclass<imp::SensorGroupRow>) @extends gtk::Widget, adw::Bin;
+}
+
+impl SensorGroupRow {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Can't create SensorGroupRow")
+    }
+
+    pub fn connect_group(&self, group: &SensorGroup) {
+        let self_ = imp::SensorGroupRow::from_instance(self);
+        let mut bindings = self_.bindings.borrow_mut();
+        let binding = group.bind_property("title", &*self_.title, "label").build();
+        bindings.push(binding.unwrap());
+        let binding = group
+            .bind_property("summary", &*self_.summary, "label")
+            .build();
+        bindings.push(binding.unwrap());
+        group.notify("title");
+        group.notify("summary");
+    }
+
+    pub fn disconnect_group(&self) {
+        let self_ = imp::SensorGroupRow::from_instance(self);
+        for binding in self_.bindings.borrow_mut().drain(..) {
+            binding.unbind();
+        }
+    }
+}
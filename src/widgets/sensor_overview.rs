@@ -1,8 +1,16 @@
-use super::SensorRow;
-use crate::{event_loop::Event, sensor_obj::SensorObj};
+use super::{SensorGroupRow, SensorRow};
+use crate::{
+    event_loop::Event,
+    sensor_group::{Grouping, SensorGroup},
+    sensor_obj::SensorObj,
+    sensor_sorter::{SensorSorter, SortKey},
+};
 use glib::subclass::prelude::*;
 use gtk::{prelude::*, CompositeTemplate};
-use std::{cell::RefCell, collections::BTreeMap};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+};
 use uuid::Uuid;
 
 mod imp {
@@ -14,7 +22,7 @@ mod imp {
     use adw::subclass::prelude::BinImpl;
     use gio::ListStore;
     use glib::{ParamSpec, Value};
-    use gtk::{subclass::prelude::*, FilterListModel, NoSelection, SingleSelection};
+    use gtk::{subclass::prelude::*, FilterListModel, NoSelection, SingleSelection, SortListModel};
 
     #[derive(Debug, CompositeTemplate)]
     #[template(resource = "/li/_5kw/BleWsGtk/sensor_overview.ui")]
@@ -22,6 +30,14 @@ mod imp {
         #[template_child]
         pub list_view: TemplateChild<gtk::ListView>,
         #[template_child]
+        pub group_by: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub sort_by: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub sort_descending: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub show_hidden: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
         pub leaflet: TemplateChild<adw::Leaflet>,
         #[template_child]
         pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
@@ -36,30 +52,61 @@ mod imp {
         pub selection_enabled: Cell<bool>,
         pub single_selection: gtk::SingleSelection,
         pub no_selection: gtk::NoSelection,
+        /// leaves out the hidden sensors unless they are to be shown
+        pub hidden_filter: gtk::CustomFilter,
+        pub visible_model: gtk::FilterListModel,
         pub filtered_model: gtk::FilterListModel,
+        pub sensor_sorter: SensorSorter,
+        pub sorted_model: gtk::SortListModel,
         pub model: gio::ListStore,
         pub rows: RefCell<BTreeMap<Uuid, SensorRow>>,
+        pub grouping: Cell<Grouping>,
+        /// [`SensorGroup`]s at the top of the tree, empty if not grouped
+        pub groups: gio::ListStore,
+        pub settings: gio::Settings,
     }
 
     impl Default for SensorOverview {
         fn default() -> Self {
             let model = ListStore::new(crate::sensor_obj::SensorObj::static_type());
+            let settings = crate::config::settings();
+            let hidden_filter =
+                gtk::CustomFilter::new(glib::clone!(@strong settings => move |item| {
+                    let sensor = item.downcast_ref::<SensorObj>().unwrap();
+                    settings.boolean("show-hidden-sensors") || !sensor.is_hidden()
+                }));
+            let visible_model = FilterListModel::new(Some(&model), Some(&hidden_filter));
             let sensor_name_filter = SensorNameFilter::new();
-            let filtered_model = FilterListModel::new(Some(&model), Some(&sensor_name_filter));
+            let filtered_model =
+                FilterListModel::new(Some(&visible_model), Some(&sensor_name_filter));
+            let sensor_sorter = SensorSorter::new();
+            let sorted_model = SortListModel::new(Some(&filtered_model), Some(&sensor_sorter));
             Self {
                 list_view: Default::default(),
-                single_selection: SingleSelection::new(Some(&filtered_model)),
-                no_selection: NoSelection::new(Some(&filtered_model)),
+                group_by: Default::default(),
+                sort_by: Default::default(),
+                sort_descending: Default::default(),
+                show_hidden: Default::default(),
+                // the models get set with the grouping
+                single_selection: SingleSelection::new(None::<&gio::ListModel>),
+                no_selection: NoSelection::new(None::<&gio::ListModel>),
                 selection_enabled: Default::default(),
                 model,
+                hidden_filter,
+                visible_model,
                 sensor_name_filter,
                 filtered_model,
+                sensor_sorter,
+                sorted_model,
                 rows: Default::default(),
                 leaflet: Default::default(),
                 scrolled_window: Default::default(),
                 pane_stack: Default::default(),
                 status_page: Default::default(),
                 overview_detail: Default::default(),
+                grouping: Default::default(),
+                groups: ListStore::new(SensorGroup::static_type()),
+                settings,
             }
         }
     }
@@ -93,6 +140,66 @@ mod imp {
                         self_.list_view.set_model(Some(&self_.single_selection));
                     }
                 }));
+
+            let grouping =
+                Grouping::from_nick(&self.settings.string("overview-grouping")).unwrap_or_default();
+            obj.set_grouping(grouping);
+            let selected = Grouping::ALL.iter().position(|g| *g == grouping).unwrap();
+            self.group_by.set_selected(selected as u32);
+            self.group_by
+                .connect_selected_notify(glib::clone!(@weak obj => move |dropdown| {
+                    let self_ = Self::from_instance(&obj);
+                    if let Some(grouping) = Grouping::ALL.get(dropdown.selected() as usize) {
+                        self_
+                            .settings
+                            .set_string("overview-grouping", grouping.nick())
+                            .unwrap();
+                        obj.set_grouping(*grouping);
+                    }
+                }));
+
+            let key =
+                SortKey::from_nick(&self.settings.string("overview-sort")).unwrap_or_default();
+            let selected = SortKey::ALL.iter().position(|k| *k == key).unwrap();
+            self.sort_by.set_selected(selected as u32);
+            self.sort_by
+                .connect_selected_notify(glib::clone!(@weak obj => move |dropdown| {
+                    let self_ = Self::from_instance(&obj);
+                    if let Some(key) = SortKey::ALL.get(dropdown.selected() as usize) {
+                        self_.settings.set_string("overview-sort", key.nick()).unwrap();
+                    }
+                }));
+            self.sort_descending.connect_active_notify(|button| {
+                button.set_icon_name(if button.is_active() {
+                    "view-sort-descending-symbolic"
+                } else {
+                    "view-sort-ascending-symbolic"
+                });
+            });
+            self.settings
+                .bind("overview-sort-descending", &*self.sort_descending, "active")
+                .build();
+            for key in &["overview-sort", "overview-sort-descending"] {
+                self.settings.connect_changed(
+                    Some(key),
+                    glib::clone!(@weak obj => move |_, _| {
+                        obj.apply_sort();
+                    }),
+                );
+            }
+            obj.apply_sort();
+
+            self.settings
+                .bind("show-hidden-sensors", &*self.show_hidden, "active")
+                .build();
+            self.settings.connect_changed(
+                Some("show-hidden-sensors"),
+                glib::clone!(@weak obj => move |_, _| {
+                    let self_ = Self::from_instance(&obj);
+                    self_.hidden_filter.changed(gtk::FilterChange::Different);
+                    obj.refresh_groups();
+                }),
+            );
         }
 
         fn properties() -> &'static [ParamSpec] {
@@ -151,6 +258,18 @@ impl SensorOverview {
         self_.overview_detail.set_displayed_sensor(sensor);
     }
 
+    pub fn refresh_units(&self) {
+        let self_ = imp::SensorOverview::from_instance(self);
+        self_.overview_detail.refresh_units();
+        self.update_group_summaries();
+    }
+
+    /// Every known sensor, regardless of the filter.
+    pub fn sensors(&self) -> gio::ListModel {
+        let self_ = imp::SensorOverview::from_instance(self);
+        self_.model.clone().upcast()
+    }
+
     pub fn set_filter(&self, filter: &str) {
         let self_ = imp::SensorOverview::from_instance(self);
         self_.sensor_name_filter.set_filter(filter);
@@ -162,9 +281,12 @@ impl SensorOverview {
         self_.single_selection.connect_selected_notify(
             glib::clone!(@weak self as this @strong tx => move |selection| {
                 let self_ = imp::SensorOverview::from_instance(&this);
-                match self_.model.item(selection.selected()) {
-                    Some(obj) => {
-                        let sensor = obj.downcast::<SensorObj>().unwrap();
+                let sensor = selection
+                    .selected_item()
+                    .and_then(|row| row.downcast::<gtk::TreeListRow>().ok()?.item())
+                    .and_then(|item| item.downcast::<SensorObj>().ok());
+                match sensor {
+                    Some(sensor) => {
                         tx.send(Event::SensorSelected(sensor.id())).unwrap();
                         tracing::trace!("Selected {}", sensor.id());
                         self_.pane_stack.set_visible_child(&*self_.overview_detail);
@@ -178,23 +300,73 @@ impl SensorOverview {
         // TODO: listen to selection_enabled and set model based on that
         self_.list_view.set_model(Some(&self_.single_selection));
 
+        // the items are rows of the tree, either a group or a sensor, the
+        // expander gets the matching widget when bound
+        let filter = self_.sensor_name_filter.clone();
         let factory = gtk::SignalListItemFactory::new();
         factory.connect_setup(move |_factory, item| {
-            item.set_child(Some(&SensorRow::connect(tx.clone())));
+            item.set_child(Some(&gtk::TreeExpander::new()));
         });
 
         factory.connect_bind(move |_factory, item| {
-            let data = item.item().unwrap().downcast::<SensorObj>().unwrap();
-            let row = item.child().unwrap().downcast::<SensorRow>().unwrap();
+            let list_row = item.item().unwrap().downcast::<gtk::TreeListRow>().unwrap();
+            let expander = item
+                .child()
+                .unwrap()
+                .downcast::<gtk::TreeExpander>()
+                .unwrap();
+            expander.set_list_row(Some(&list_row));
 
-            row.connect_sensor_obj(&data);
+            let data = list_row.item().unwrap();
+            if let Some(data) = data.downcast_ref::<SensorObj>() {
+                let row = match expander
+                    .child()
+                    .and_then(|c| c.downcast::<SensorRow>().ok())
+                {
+                    Some(row) => row,
+                    None => {
+                        let row = SensorRow::connect(tx.clone());
+                        row.set_filter(&filter);
+                        expander.set_child(Some(&row));
+                        row
+                    }
+                };
+                row.connect_sensor_obj(data);
+                item.set_selectable(true);
+            } else if let Some(group) = data.downcast_ref::<SensorGroup>() {
+                let row = match expander
+                    .child()
+                    .and_then(|c| c.downcast::<SensorGroupRow>().ok())
+                {
+                    Some(row) => row,
+                    None => {
+                        let row = SensorGroupRow::new();
+                        expander.set_child(Some(&row));
+                        row
+                    }
+                };
+                row.connect_group(group);
+                item.set_selectable(false);
+            }
         });
 
         factory.connect_unbind(move |_facotyr, item| {
-            let data = item.item().unwrap().downcast::<SensorObj>().unwrap();
-            let row = item.child().unwrap().downcast::<SensorRow>().unwrap();
+            let list_row = item.item().unwrap().downcast::<gtk::TreeListRow>().unwrap();
+            let expander = item
+                .child()
+                .unwrap()
+                .downcast::<gtk::TreeExpander>()
+                .unwrap();
+            let data = list_row.item().unwrap();
 
-            row.disconnect_sensor_obj(&data);
+            if let Some(child) = expander.child() {
+                if let Some(row) = child.downcast_ref::<SensorRow>() {
+                    row.disconnect_sensor_obj(data.downcast_ref::<SensorObj>().unwrap());
+                } else if let Some(row) = child.downcast_ref::<SensorGroupRow>() {
+                    row.disconnect_group();
+                }
+            }
+            expander.set_list_row(None);
         });
 
         self_.list_view.set_factory(Some(&factory));
@@ -203,5 +375,124 @@ impl SensorOverview {
     pub fn add_sensor(&self, sensor: &SensorObj) {
         let self_ = imp::SensorOverview::from_instance(self);
         self_.model.append(sensor);
+
+        // the groups depend on the metadata and their summaries on the values,
+        // the hidden and favorite flags are settings as well
+        sensor.connect_notify_local(
+            Some("settings"),
+            glib::clone!(@weak self as this => move |_, _| {
+                let self_ = imp::SensorOverview::from_instance(&this);
+                self_.hidden_filter.changed(gtk::FilterChange::Different);
+                self_.sensor_name_filter.refilter();
+                self_.sensor_sorter.resort();
+                this.refresh_groups();
+            }),
+        );
+        for property in &["label", "connected", "last-update", "corrections"] {
+            sensor.connect_notify_local(
+                Some(property),
+                glib::clone!(@weak self as this => move |_, pspec| {
+                    let self_ = imp::SensorOverview::from_instance(&this);
+                    // the search can compare values
+                    self_.sensor_name_filter.refilter();
+                    if self_.sensor_sorter.key().properties().contains(&pspec.name()) {
+                        self_.sensor_sorter.resort();
+                    }
+                }),
+            );
+        }
+        for property in &["connected", "temperature", "humidity", "corrections"] {
+            sensor.connect_notify_local(
+                Some(property),
+                glib::clone!(@weak self as this => move |_, _| {
+                    this.update_group_summaries();
+                }),
+            );
+        }
+        self.refresh_groups();
+    }
+
+    pub fn set_grouping(&self, grouping: Grouping) {
+        let self_ = imp::SensorOverview::from_instance(self);
+        self_.grouping.set(grouping);
+        self_.groups.remove_all();
+        self.refresh_groups();
+
+        let root: gio::ListModel = match grouping {
+            Grouping::None => self_.sorted_model.clone().upcast(),
+            _ => self_.groups.clone().upcast(),
+        };
+        let tree_model = gtk::TreeListModel::new(&root, false, true, |item| {
+            item.downcast_ref::<SensorGroup>()
+                .map(|group| group.model())
+        });
+        self_.single_selection.set_model(Some(&tree_model));
+        self_.no_selection.set_model(Some(&tree_model));
+    }
+
+    /// Add and remove groups after sensors or their metadata changed, the
+    /// groups are sorted by name with the sensors without a room or tag last.
+    /// Groups of only hidden sensors are left out.
+    fn refresh_groups(&self) {
+        let self_ = imp::SensorOverview::from_instance(self);
+        let grouping = self_.grouping.get();
+        if grouping == Grouping::None {
+            return;
+        }
+
+        let keys = (0..self_.visible_model.n_items())
+            .filter_map(|i| self_.visible_model.item(i)?.downcast::<SensorObj>().ok())
+            .flat_map(|sensor| grouping.keys(&sensor.settings().metadata))
+            .collect::<BTreeSet<_>>();
+        let mut keys = keys.into_iter().collect::<Vec<_>>();
+        if keys.first().map_or(false, String::is_empty) {
+            keys.rotate_left(1);
+        }
+
+        // edit the groups in place so the others stay expanded or collapsed
+        for i in (0..self_.groups.n_items()).rev() {
+            let group = self_
+                .groups
+                .item(i)
+                .unwrap()
+                .downcast::<SensorGroup>()
+                .unwrap();
+            if keys.contains(&group.key()) {
+                group.refilter();
+            } else {
+                self_.groups.remove(i);
+            }
+        }
+        for (i, key) in keys.iter().enumerate() {
+            let exists = self_
+                .groups
+                .item(i as u32)
+                .and_then(|group| group.downcast::<SensorGroup>().ok())
+                .map_or(false, |group| group.key() == *key);
+            if !exists {
+                let group = SensorGroup::new(grouping, key, &self_.sorted_model);
+                self_.groups.insert(i as u32, &group);
+            }
+        }
+    }
+
+    fn apply_sort(&self) {
+        let self_ = imp::SensorOverview::from_instance(self);
+        let key = SortKey::from_nick(&self_.settings.string("overview-sort")).unwrap_or_default();
+        let descending = self_.settings.boolean("overview-sort-descending");
+        self_.sensor_sorter.set_sort(key, descending);
+    }
+
+    fn update_group_summaries(&self) {
+        let self_ = imp::SensorOverview::from_instance(self);
+        for i in 0..self_.groups.n_items() {
+            if let Some(group) = self_
+                .groups
+                .item(i)
+                .and_then(|group| group.downcast::<SensorGroup>().ok())
+            {
+                group.update_summary();
+            }
+        }
     }
 }
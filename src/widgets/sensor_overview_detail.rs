@@ -6,9 +6,10 @@ use super::graph;
 
 mod imp {
     use super::*;
-    use crate::widgets::{
-        graph::{GraphPainter, Unit},
-        Graph,
+    use crate::{
+        forecast::Tendency,
+        units::Unit,
+        widgets::{graph::GraphPainter, Graph, TendencyIndicator},
     };
     use glib::{ParamFlags, ParamSpec, Value};
     use gtk::subclass::prelude::*;
@@ -20,6 +21,8 @@ mod imp {
         #[template_child]
         sensor_label: TemplateChild<gtk::Label>,
         #[template_child]
+        tendency: TemplateChild<TendencyIndicator>,
+        #[template_child]
         temperature_graph: TemplateChild<Graph>,
         #[template_child]
         humidity_graph: TemplateChild<Graph>,
@@ -28,7 +31,7 @@ mod imp {
 
         pub painters: [GraphPainter; 3],
         displayed_sensor: RefCell<Option<SensorObj>>,
-        sensor_binding: RefCell<Option<glib::Binding>>,
+        sensor_bindings: RefCell<Vec<glib::Binding>>,
     }
 
     #[glib::object_subclass]
@@ -68,8 +71,12 @@ mod imp {
                         .get()
                         .expect("type conformity checked by `Object::set_property`");
                     self.displayed_sensor.replace(sensor);
+                    for binding in self.sensor_bindings.borrow_mut().drain(..) {
+                        binding.unbind();
+                    }
                     let sensor = self.displayed_sensor.borrow();
                     if let Some(sensor) = &*sensor {
+                        let mut bindings = self.sensor_bindings.borrow_mut();
                         let binding = sensor
                             .bind_property("label", &*self.sensor_label, "label")
                             .transform_to(|_, val| {
@@ -77,15 +84,17 @@ mod imp {
                                 Some(val.unwrap_or_default().to_value())
                             })
                             .build();
+                        bindings.extend(binding);
+                        let binding = sensor
+                            .bind_property("tendency", &*self.tendency, "tendency")
+                            .build();
+                        bindings.extend(binding);
                         sensor.notify("label");
-
-                        if let Some(old_binding) = self.sensor_binding.replace(binding) {
-                            old_binding.unbind()
-                        }
+                        sensor.notify("tendency");
                     } else {
-                        if let Some(binding) = self.sensor_binding.take() {
-                            binding.unbind();
-                        }
+                        self.tendency
+                            .set_property("tendency", &None::<Tendency>)
+                            .unwrap();
                     }
                 }
                 _ => unimplemented!(),
@@ -130,4 +139,11 @@ impl SensorOverviewDetail {
             painter.set_displayed_data(sensor.clone().map(graph::Data::Live));
         }
     }
+
+    pub fn refresh_units(&self) {
+        let self_ = imp::SensorOverviewDetail::from_instance(self);
+        for painter in &self_.painters {
+            painter.invalidate_contents();
+        }
+    }
 }
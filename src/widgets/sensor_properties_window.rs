@@ -0,0 +1,162 @@
+use crate::{
+    event_loop::Event,
+    local_store::{SensorColor, SensorMetadata},
+    sensor_obj::SensorObj,
+};
+use glib::subclass::prelude::*;
+use gtk::prelude::*;
+use uuid::Uuid;
+
+mod imp {
+    use super::*;
+    use adw::subclass::prelude::*;
+    use glib::signal::Inhibit;
+    use gtk::{subclass::prelude::*, CompositeTemplate};
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/li/_5kw/BleWsGtk/sensor_properties_window.ui")]
+    pub struct SensorPropertiesWindow {
+        #[template_child]
+        pub room: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub tags: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub icon: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub color: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub notes: TemplateChild<gtk::TextView>,
+
+        pub id: Cell<Uuid>,
+        /// metadata when the window was opened
+        pub metadata: RefCell<SensorMetadata>,
+        pub tx: RefCell<Option<glib::Sender<Event>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SensorPropertiesWindow {
+        const NAME: &'static str = "BleWsSensorPropertiesWindow";
+        type Type = super::SensorPropertiesWindow;
+        type ParentType = adw::PreferencesWindow;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for SensorPropertiesWindow {}
+    impl WidgetImpl for SensorPropertiesWindow {}
+    impl WindowImpl for SensorPropertiesWindow {
+        // the changes are saved at once when the window gets closed
+        fn close_request(&self, obj: &Self::Type) -> Inhibit {
+            let metadata = obj.metadata();
+            if metadata != *self.metadata.borrow() {
+                if let Some(tx) = &*self.tx.borrow() {
+                    tx.send(Event::ChangeMetadata {
+                        id: self.id.get(),
+                        metadata,
+                    })
+                    .unwrap();
+                }
+            }
+            self.parent_close_request(obj)
+        }
+    }
+    impl AdwWindowImpl for SensorPropertiesWindow {}
+    impl PreferencesWindowImpl for SensorPropertiesWindow {}
+}
+
+glib::wrapper! {
+    pub struct SensorPropertiesWindow(ObjectSubclass<imp::SensorPropertiesWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window, adw::PreferencesWindow;
+}
+
+/// Icons that can be picked, in the order of the dropdown.
+const ICONS: [Option<&str>; 8] = [
+    None,
+    Some("user-home-symbolic"),
+    Some("weather-clear-symbolic"),
+    Some("weather-overcast-symbolic"),
+    Some("weather-snow-symbolic"),
+    Some("computer-symbolic"),
+    Some("applications-science-symbolic"),
+    Some("starred-symbolic"),
+];
+
+impl SensorPropertiesWindow {
+    pub fn new(sensor: &SensorObj, tx: glib::Sender<Event>) -> Self {
+        let ret: Self = glib::Object::new(&[]).unwrap();
+        let self_ = imp::SensorPropertiesWindow::from_instance(&ret);
+        let label = sensor
+            .data()
+            .label
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| sensor.id().to_string());
+        ret.set_title(Some(&format!("Properties of {}", label)));
+        self_.id.set(sensor.id());
+        self_.tx.replace(Some(tx));
+        ret.set_metadata(sensor.settings().metadata);
+        ret
+    }
+
+    /// Open the properties of `sensor` on top of the window `widget` is in.
+    pub fn open(sensor: &SensorObj, tx: glib::Sender<Event>, widget: &impl IsA<gtk::Widget>) {
+        let window = Self::new(sensor, tx);
+        let parent = widget
+            .root()
+            .and_then(|root| root.downcast::<gtk::Window>().ok());
+        window.set_transient_for(parent.as_ref());
+        window.show();
+    }
+
+    fn set_metadata(&self, metadata: SensorMetadata) {
+        let self_ = imp::SensorPropertiesWindow::from_instance(self);
+        self_.room.set_text(&metadata.room);
+        self_.tags.set_text(&metadata.tags.join(", "));
+        let icon = ICONS
+            .iter()
+            .position(|icon| *icon == metadata.icon.as_deref())
+            .unwrap_or(0);
+        self_.icon.set_selected(icon as u32);
+        // the first entry is no color
+        let color = metadata
+            .color
+            .and_then(|color| SensorColor::ALL.iter().position(|c| *c == color))
+            .map_or(0, |i| i + 1);
+        self_.color.set_selected(color as u32);
+        self_.notes.buffer().set_text(&metadata.notes);
+        self_.metadata.replace(metadata);
+    }
+
+    fn metadata(&self) -> SensorMetadata {
+        let self_ = imp::SensorPropertiesWindow::from_instance(self);
+        let mut tags = Vec::<String>::new();
+        for tag in self_.tags.text().split(',').map(str::trim) {
+            if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_owned());
+            }
+        }
+        let buffer = self_.notes.buffer();
+        let (start, end) = buffer.bounds();
+        SensorMetadata {
+            room: self_.room.text().trim().to_owned(),
+            notes: buffer.text(&start, &end, false).to_string(),
+            tags,
+            icon: ICONS
+                .get(self_.icon.selected() as usize)
+                .copied()
+                .flatten()
+                .map(str::to_owned),
+            color: (self_.color.selected() as usize)
+                .checked_sub(1)
+                .and_then(|i| SensorColor::ALL.get(i))
+                .copied(),
+        }
+    }
+}
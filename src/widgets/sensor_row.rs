@@ -1,11 +1,16 @@
-use std::convert::TryFrom;
-
 use crate::{
+    data::Corrections,
     event_loop::{Event, Label},
+    local_store::{SensorColor, SensorMetadata, SensorSettings},
+    search::Query,
+    sensor_name_filter::SensorNameFilter,
     sensor_obj::SensorObj,
+    units::Unit,
+    widgets::SensorPropertiesWindow,
 };
 use glib::subclass::prelude::*;
 use gtk::{prelude::*, CompositeTemplate};
+use std::ops::Range;
 
 mod imp {
     use std::cell::RefCell;
@@ -25,6 +30,12 @@ mod imp {
         #[template_child]
         pub pressure: TemplateChild<gtk::Label>,
         #[template_child]
+        pub tendency: TemplateChild<crate::widgets::TendencyIndicator>,
+        #[template_child]
+        pub calibration: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub icon: TemplateChild<gtk::Image>,
+        #[template_child]
         pub sensor_label: TemplateChild<gtk::EditableLabel>,
         #[template_child]
         pub sensor_id: TemplateChild<gtk::Label>,
@@ -32,10 +43,22 @@ mod imp {
         pub info_stack: TemplateChild<gtk::Stack>,
         #[template_child]
         pub detail_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub properties_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub favorite_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub hide_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub metadata: TemplateChild<gtk::Label>,
 
         pub sensor_obj: RefCell<Option<SensorObj>>,
         pub bindings: RefCell<Vec<glib::Binding>>,
         pub edit_handler_id: RefCell<Option<SignalHandlerId>>,
+        /// handlers of the label and settings changes of the sensor
+        pub notify_handler_ids: RefCell<Vec<SignalHandlerId>>,
+        /// search to highlight the matches of
+        pub filter: RefCell<Option<SensorNameFilter>>,
 
         pub tx: RefCell<Option<glib::Sender<Event>>>,
     }
@@ -83,6 +106,37 @@ impl SensorRow {
                     tx.send(Event::OpenDetail(obj.id())).unwrap();
                 }
             }));
+        self_
+            .properties_button
+            .connect_clicked(glib::clone!(@weak ret, @strong tx => move |_| {
+                let self_ = imp::SensorRow::from_instance(&ret);
+                if let Some(obj) = &*self_.sensor_obj.borrow() {
+                    SensorPropertiesWindow::open(obj, tx.clone(), &ret);
+                }
+            }));
+        // the buttons follow the settings, only clicks differ from them
+        self_.favorite_button.connect_toggled(
+            glib::clone!(@weak ret, @strong tx => move |button| {
+                let self_ = imp::SensorRow::from_instance(&ret);
+                if let Some(obj) = &*self_.sensor_obj.borrow() {
+                    let favorite = button.is_active();
+                    if obj.is_favorite() != favorite {
+                        tx.send(Event::ChangeFavorite { id: obj.id(), favorite }).unwrap();
+                    }
+                }
+            }),
+        );
+        self_
+            .hide_button
+            .connect_toggled(glib::clone!(@weak ret, @strong tx => move |button| {
+                let self_ = imp::SensorRow::from_instance(&ret);
+                if let Some(obj) = &*self_.sensor_obj.borrow() {
+                    let hidden = button.is_active();
+                    if obj.is_hidden() != hidden {
+                        tx.send(Event::ChangeHidden { id: obj.id(), hidden }).unwrap();
+                    }
+                }
+            }));
         ret
     }
 
@@ -91,52 +145,80 @@ impl SensorRow {
         let mut bindings = self_.bindings.borrow_mut();
         // FIXME: trying to do this generically is a major pain, think carefully
         // before trying to refactor this
+        // the values are corrected with the other values of the sensor, so
+        // the transforms need the whole sensor
         let binding = sensor_obj
             .bind_property("temperature", &*self_.temperature, "label")
-            .transform_to(|_, val| {
-                let val = val.get::<i32>().unwrap();
-                let converted = ble_ws_api::data::Celsius::try_from(val as i16)
-                    .unwrap()
-                    .to_string();
-                Some(converted.to_value())
+            .transform_to(|binding, _| {
+                let sensor = binding.source()?.downcast::<SensorObj>().ok()?;
+                let value = sensor.values().get(Unit::Temperature);
+                Some(Unit::Temperature.format_value(value).to_value())
             })
             .build();
         bindings.push(binding.unwrap());
 
         let binding = sensor_obj
             .bind_property("pressure", &*self_.pressure, "label")
-            .transform_to(|_, val| {
-                let val = val.get::<u32>().unwrap();
-                let converted = ble_ws_api::data::Pascal::from(val).to_string();
-                Some(converted.to_value())
+            .transform_to(|binding, _| {
+                let sensor = binding.source()?.downcast::<SensorObj>().ok()?;
+                let value = sensor.values().get(Unit::Pressure);
+                Some(Unit::Pressure.format_value(value).to_value())
             })
             .build();
         bindings.push(binding.unwrap());
 
         let binding = sensor_obj
-            .bind_property("humidity", &*self_.humidity, "label")
+            .bind_property("corrections", &*self_.pressure, "tooltip-text")
             .transform_to(|_, val| {
-                let val = val.get::<u32>().unwrap();
-                let converted = ble_ws_api::data::RelativeHumidity::try_from(val as u16)
-                    .unwrap()
-                    .to_string();
-                Some(converted.to_value())
+                let val = val.get::<Corrections>().unwrap();
+                let tooltip = val
+                    .sea_level
+                    .map(|(altitude, _)| format!("Reduced to sea level from {} m", altitude));
+                Some(tooltip.to_value())
             })
             .build();
+        bindings.push(binding.unwrap());
 
+        let binding = sensor_obj
+            .bind_property("corrections", &*self_.calibration, "label")
+            .transform_to(|_, val| {
+                let val = val.get::<Corrections>().unwrap();
+                Some(val.calibration_label().unwrap_or_default().to_value())
+            })
+            .build();
         bindings.push(binding.unwrap());
 
         let binding = sensor_obj
-            .bind_property("label", &*self_.sensor_label, "text")
+            .bind_property("corrections", &*self_.calibration, "visible")
             .transform_to(|_, val| {
-                let val = val.get::<Option<String>>().unwrap();
-                Some(val.unwrap_or_default().to_value())
+                let val = val.get::<Corrections>().unwrap();
+                Some(val.calibration_label().is_some().to_value())
             })
             .build();
         bindings.push(binding.unwrap());
 
         let binding = sensor_obj
-            .bind_property("id", &*self_.sensor_id, "label")
+            .bind_property("tendency", &*self_.tendency, "tendency")
+            .build();
+        bindings.push(binding.unwrap());
+
+        let binding = sensor_obj
+            .bind_property("humidity", &*self_.humidity, "label")
+            .transform_to(|binding, _| {
+                let sensor = binding.source()?.downcast::<SensorObj>().ok()?;
+                let value = sensor.values().get(Unit::Humidity);
+                Some(Unit::Humidity.format_value(value).to_value())
+            })
+            .build();
+
+        bindings.push(binding.unwrap());
+
+        let binding = sensor_obj
+            .bind_property("label", &*self_.sensor_label, "text")
+            .transform_to(|_, val| {
+                let val = val.get::<Option<String>>().unwrap();
+                Some(val.unwrap_or_default().to_value())
+            })
             .build();
         bindings.push(binding.unwrap());
 
@@ -154,8 +236,9 @@ impl SensorRow {
         sensor_obj.notify("connected");
         sensor_obj.notify("temperature");
         sensor_obj.notify("pressure");
+        sensor_obj.notify("corrections");
+        sensor_obj.notify("tendency");
         sensor_obj.notify("humidity");
-        sensor_obj.notify("id");
         sensor_obj.notify("label");
 
         let tx = self_.tx.borrow().as_ref().unwrap().clone();
@@ -171,7 +254,26 @@ impl SensorRow {
         });
         self_.edit_handler_id.replace(Some(edit_handler_id));
 
+        let mut notify_handler_ids = self_.notify_handler_ids.borrow_mut();
+        for property in &["label", "settings"] {
+            notify_handler_ids.push(sensor_obj.connect_notify_local(
+                Some(property),
+                glib::clone!(@weak self as this => move |_, _| {
+                    this.refresh_highlights();
+                }),
+            ));
+        }
+        notify_handler_ids.push(sensor_obj.connect_notify_local(
+            Some("settings"),
+            glib::clone!(@weak self as this => move |sensor, _| {
+                this.set_flags(&sensor.settings());
+            }),
+        ));
+        drop(notify_handler_ids);
+
         *self_.sensor_obj.borrow_mut() = Some(sensor_obj.clone());
+        self.set_flags(&sensor_obj.settings());
+        self.refresh_highlights();
     }
 
     pub fn disconnect_sensor_obj(&self, sensor_obj: &SensorObj) {
@@ -189,6 +291,117 @@ impl SensorRow {
         }
         self_.edit_handler_id.replace(None);
 
+        for handler_id in self_.notify_handler_ids.borrow_mut().drain(..) {
+            sensor_obj.disconnect(handler_id);
+        }
+
         *self_.sensor_obj.borrow_mut() = None;
     }
+
+    /// Highlight the matches of the search of `filter` from now on.
+    pub fn set_filter(&self, filter: &SensorNameFilter) {
+        let self_ = imp::SensorRow::from_instance(self);
+        filter.connect_changed(glib::clone!(@weak self as this => move |_, _| {
+            this.refresh_highlights();
+        }));
+        self_.filter.replace(Some(filter.clone()));
+        self.refresh_highlights();
+    }
+
+    fn refresh_highlights(&self) {
+        let self_ = imp::SensorRow::from_instance(self);
+        let sensor = match &*self_.sensor_obj.borrow() {
+            Some(sensor) => sensor.clone(),
+            None => return,
+        };
+        let query = self_
+            .filter
+            .borrow()
+            .as_ref()
+            .map(SensorNameFilter::query)
+            .unwrap_or_default();
+
+        // the editable label can't show markup, highlight it as a whole
+        let label = sensor.data().label.borrow().clone().unwrap_or_default();
+        if query.highlights(&label, true).is_empty() {
+            self_.sensor_label.remove_css_class("search-match");
+        } else {
+            self_.sensor_label.add_css_class("search-match");
+        }
+        // ids are only searched by their start
+        let id = sensor.id().to_string();
+        let mut id_highlights = query.highlights(&id, false);
+        id_highlights.retain(|range| range.start == 0);
+        self_
+            .sensor_id
+            .set_markup(&highlight_markup(&id, &id_highlights));
+        self.set_metadata(&sensor.settings().metadata, &query);
+    }
+
+    fn set_flags(&self, settings: &SensorSettings) {
+        let self_ = imp::SensorRow::from_instance(self);
+        self_.favorite_button.set_active(settings.favorite);
+        self_.favorite_button.set_icon_name(if settings.favorite {
+            "starred-symbolic"
+        } else {
+            "non-starred-symbolic"
+        });
+        self_.hide_button.set_active(settings.hidden);
+        self_.hide_button.set_tooltip_text(Some(if settings.hidden {
+            "Show sensor"
+        } else {
+            "Hide sensor"
+        }));
+        // hidden sensors are only listed when asked for
+        if settings.hidden {
+            self.add_css_class("dim-label");
+        } else {
+            self.remove_css_class("dim-label");
+        }
+    }
+
+    fn set_metadata(&self, metadata: &SensorMetadata, query: &Query) {
+        let self_ = imp::SensorRow::from_instance(self);
+        self_.icon.set_icon_name(metadata.icon.as_deref());
+        self_.icon.set_visible(metadata.icon.is_some());
+        for color in SensorColor::ALL.iter() {
+            self_.icon.remove_css_class(color.css_class());
+        }
+        if let Some(color) = metadata.color {
+            self_.icon.add_css_class(color.css_class());
+        }
+
+        // fall back to the beginning of the notes, the tooltip has all of them
+        let mut summary = metadata.summary();
+        if summary.is_empty() {
+            summary = metadata.notes.lines().next().unwrap_or_default().to_owned();
+        }
+        self_.metadata.set_markup(&highlight_markup(
+            &summary,
+            &query.highlights(&summary, false),
+        ));
+        self_.metadata.set_visible(!summary.is_empty());
+        self_
+            .metadata
+            .set_tooltip_text(if metadata.notes.is_empty() {
+                None
+            } else {
+                Some(&metadata.notes)
+            });
+    }
+}
+
+/// `text` as markup with the `ranges` in bold.
+fn highlight_markup(text: &str, ranges: &[Range<usize>]) -> String {
+    let mut markup = String::new();
+    let mut end = 0;
+    for range in ranges {
+        markup.push_str(&glib::markup_escape_text(&text[end..range.start]));
+        markup.push_str("<b>");
+        markup.push_str(&glib::markup_escape_text(&text[range.clone()]));
+        markup.push_str("</b>");
+        end = range.end;
+    }
+    markup.push_str(&glib::markup_escape_text(&text[end..]));
+    markup
 }
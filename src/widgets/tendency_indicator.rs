@@ -0,0 +1,112 @@
+use crate::{forecast::Tendency, units::Unit};
+use glib::subclass::prelude::*;
+use gtk::{prelude::*, CompositeTemplate};
+
+mod imp {
+    use super::*;
+    use adw::subclass::prelude::*;
+    use glib::ParamFlags;
+    use gtk::subclass::prelude::*;
+    use once_cell::sync::Lazy;
+    use std::cell::Cell;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/li/_5kw/BleWsGtk/tendency_indicator.ui")]
+    pub struct TendencyIndicator {
+        #[template_child]
+        pub icon: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub forecast: TemplateChild<gtk::Label>,
+
+        pub tendency: Cell<Option<Tendency>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TendencyIndicator {
+        const NAME: &'static str = "BleWsTendencyIndicator";
+        type Type = super::TendencyIndicator;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for TendencyIndicator {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpec::new_boxed(
+                    "tendency",
+                    "Tendency",
+                    "Pressure tendency to show, hidden if unset",
+                    Tendency::static_type(),
+                    ParamFlags::READWRITE,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn property(&self, _obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "tendency" => self.tendency.get().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "tendency" => {
+                    let tendency = value
+                        .get()
+                        .expect("type conformity checked by `Object::set_property`");
+                    self.tendency.set(tendency);
+                    obj.refresh();
+                }
+                _ => unimplemented!(),
+            }
+        }
+    }
+
+    impl WidgetImpl for TendencyIndicator {}
+    impl BinImpl for TendencyIndicator {}
+}
+
+glib::wrapper! {
+    pub struct TendencyIndicator(ObjectSubclass<imp::TendencyIndicator>) @extends gtk::Widget, adw::Bin;
+}
+
+impl TendencyIndicator {
+    fn refresh(&self) {
+        let self_ = imp::TendencyIndicator::from_instance(self);
+        let tendency = match self_.tendency.get() {
+            Some(tendency) => tendency,
+            None => {
+                self.set_visible(false);
+                return;
+            }
+        };
+        let change = Unit::Pressure.format_delta(tendency.change * Unit::Pressure.scale());
+        let sign = if tendency.change > 0. { "+" } else { "" };
+        self_.icon.set_icon_name(Some(tendency.trend.icon_name()));
+        self_.label.set_label(&format!(
+            "{}, {}{} in 3 h",
+            tendency.trend.label(),
+            sign,
+            change
+        ));
+        self_.forecast.set_label(tendency.forecast());
+        self.set_visible(true);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    local_store::{Threshold, Thresholds},
+    units::{Unit, UnitSystem},
+};
+use glib::subclass::prelude::*;
+use gtk::{prelude::*, CompositeTemplate};
+
+mod imp {
+    use super::*;
+    use adw::subclass::prelude::BinImpl;
+    use gtk::subclass::prelude::*;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(resource = "/li/_5kw/BleWsGtk/threshold_editor.ui")]
+    pub struct ThresholdEditor {
+        #[template_child]
+        pub temperature_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub pressure_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub temperature_lower_enabled: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub temperature_lower: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub temperature_upper_enabled: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub temperature_upper: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub humidity_lower_enabled: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub humidity_lower: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub humidity_upper_enabled: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub humidity_upper: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pressure_lower_enabled: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub pressure_lower: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pressure_upper_enabled: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub pressure_upper: TemplateChild<gtk::SpinButton>,
+
+        /// set while the widgets get filled programmatically
+        pub updating: Cell<bool>,
+        /// units the limits are entered in
+        pub units: Cell<UnitSystem>,
+        pub changed_cb: RefCell<Option<Box<dyn Fn(Thresholds)>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ThresholdEditor {
+        const NAME: &'static str = "BleWsThresholdEditor";
+        type Type = super::ThresholdEditor;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ThresholdEditor {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            for row in obj.rows().iter() {
+                for (enabled, value) in &[(row.1, row.2), (row.3, row.4)] {
+                    enabled.connect_toggled(glib::clone!(@weak obj => move |_| {
+                        obj.emit_changed();
+                    }));
+                    value.connect_value_changed(glib::clone!(@weak obj => move |_| {
+                        obj.emit_changed();
+                    }));
+                }
+            }
+            obj.set_units(UnitSystem::current());
+        }
+    }
+
+    impl WidgetImpl for ThresholdEditor {}
+    impl BinImpl for ThresholdEditor {}
+}
+
+glib::wrapper! {
+    pub struct ThresholdEditor(ObjectSubclass<imp::ThresholdEditor>) @extends gtk::Widget, adw::Bin;
+}
+
+/// Raw values the limits of `unit` can be set to and the raw step between them.
+fn raw_range(unit: Unit) -> (f64, f64, f64) {
+    match unit {
+        Unit::Temperature => (-4000., 8500., 50.),
+        Unit::Humidity => (0., 10000., 50.),
+        Unit::Pressure => (300000., 1100000., 500.),
+        _ => unreachable!("only measured units have thresholds"),
+    }
+}
+
+type Row<'a> = (
+    Unit,
+    &'a gtk::CheckButton,
+    &'a gtk::SpinButton,
+    &'a gtk::CheckButton,
+    &'a gtk::SpinButton,
+);
+
+impl ThresholdEditor {
+    fn rows(&self) -> [Row<'_>; 3] {
+        let self_ = imp::ThresholdEditor::from_instance(self);
+        [
+            (
+                Unit::Temperature,
+                &self_.temperature_lower_enabled,
+                &self_.temperature_lower,
+                &self_.temperature_upper_enabled,
+                &self_.temperature_upper,
+            ),
+            (
+                Unit::Humidity,
+                &self_.humidity_lower_enabled,
+                &self_.humidity_lower,
+                &self_.humidity_upper_enabled,
+                &self_.humidity_upper,
+            ),
+            (
+                Unit::Pressure,
+                &self_.pressure_lower_enabled,
+                &self_.pressure_lower,
+                &self_.pressure_upper_enabled,
+                &self_.pressure_upper,
+            ),
+        ]
+    }
+
+    pub fn thresholds(&self) -> Thresholds {
+        let self_ = imp::ThresholdEditor::from_instance(self);
+        let units = self_.units.get();
+        let mut ret = Thresholds::default();
+        for (unit, lower_enabled, lower, upper_enabled, upper) in self.rows().iter() {
+            let limit = |enabled: &gtk::CheckButton, value: &gtk::SpinButton| {
+                if enabled.is_active() {
+                    Some(units.to_raw(*unit, value.value()))
+                } else {
+                    None
+                }
+            };
+            if let Some(threshold) = ret.get_mut(*unit) {
+                *threshold = Threshold {
+                    lower: limit(lower_enabled, lower),
+                    upper: limit(upper_enabled, upper),
+                };
+            }
+        }
+        ret
+    }
+
+    /// Display `thresholds` without emitting a change.
+    pub fn set_thresholds(&self, thresholds: Thresholds) {
+        let self_ = imp::ThresholdEditor::from_instance(self);
+        let units = self_.units.get();
+        self_.updating.set(true);
+        for (unit, lower_enabled, lower, upper_enabled, upper) in self.rows().iter() {
+            let threshold = thresholds.get(*unit);
+            lower_enabled.set_active(threshold.lower.is_some());
+            if let Some(value) = threshold.lower {
+                lower.set_value(units.convert(*unit, value));
+            }
+            upper_enabled.set_active(threshold.upper.is_some());
+            if let Some(value) = threshold.upper {
+                upper.set_value(units.convert(*unit, value));
+            }
+        }
+        self_.updating.set(false);
+    }
+
+    /// Enter the limits in `units` from now on, keeping the current limits.
+    pub fn set_units(&self, units: UnitSystem) {
+        let self_ = imp::ThresholdEditor::from_instance(self);
+        let thresholds = self.thresholds();
+        self_.units.set(units);
+
+        self_.updating.set(true);
+        self_.temperature_label.set_label(&format!(
+            "Temperature ({})",
+            units.symbol(Unit::Temperature)
+        ));
+        self_
+            .pressure_label
+            .set_label(&format!("Pressure ({})", units.symbol(Unit::Pressure)));
+        for (unit, _, lower, _, upper) in self.rows().iter() {
+            let (min, max, step) = raw_range(*unit);
+            for spin_button in &[lower, upper] {
+                let adjustment = spin_button.adjustment();
+                adjustment.set_lower(units.convert(*unit, min));
+                adjustment.set_upper(units.convert(*unit, max));
+                adjustment.set_step_increment(units.convert_delta(*unit, step));
+                adjustment.set_page_increment(units.convert_delta(*unit, step * 10.));
+                spin_button.set_digits(units.precision(*unit) as u32);
+            }
+        }
+        self_.updating.set(false);
+
+        self.set_thresholds(thresholds);
+    }
+
+    pub fn connect_changed<F>(&self, cb: F)
+    where
+        F: Fn(Thresholds) + 'static,
+    {
+        let self_ = imp::ThresholdEditor::from_instance(self);
+        *self_.changed_cb.borrow_mut() = Some(Box::new(cb));
+    }
+
+    fn emit_changed(&self) {
+        let self_ = imp::ThresholdEditor::from_instance(self);
+        if self_.updating.get() {
+            return;
+        }
+        if let Some(cb) = &*self_.changed_cb.borrow() {
+            cb(self.thresholds());
+        }
+    }
+}
@@ -1,5 +1,6 @@
-use super::{SensorDetail, SensorOverview};
+use super::{AlertHistory, SensorComparison, SensorDetail, SensorOverview};
 use crate::{
+    alerts::HistoryEntry,
     data::Timeseries,
     event_loop::{Event, View},
 };
@@ -19,12 +20,18 @@ mod imp {
         #[template_child]
         pub overview_graph_button: TemplateChild<gtk::Button>,
         #[template_child]
+        pub alerts_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub search_button: TemplateChild<gtk::ToggleButton>,
         #[template_child]
         pub sensor_overview: TemplateChild<SensorOverview>,
         #[template_child]
         pub sensor_detail: TemplateChild<SensorDetail>,
         #[template_child]
+        pub sensor_comparison: TemplateChild<SensorComparison>,
+        #[template_child]
+        pub alert_history: TemplateChild<AlertHistory>,
+        #[template_child]
         pub main_stack: TemplateChild<gtk::Stack>,
         #[template_child]
         pub view_switcher_title: TemplateChild<adw::ViewSwitcherTitle>,
@@ -56,9 +63,12 @@ mod imp {
             Self {
                 previous_button: Default::default(),
                 overview_graph_button: Default::default(),
+                alerts_button: Default::default(),
                 search_button: Default::default(),
                 sensor_overview: Default::default(),
                 sensor_detail: Default::default(),
+                sensor_comparison: Default::default(),
+                alert_history: Default::default(),
                 main_stack: Default::default(),
                 view_switcher_title: Default::default(),
                 burger_button: Default::default(),
@@ -110,6 +120,17 @@ mod imp {
             if let Err(err) = obj.save_window_state() {
                 tracing::error!("Failed to save window state, {}", &err);
             }
+            // keep the window and the services alive, the next activation shows it again
+            if self.settings.boolean("run-in-background") {
+                if let Some(app) = obj
+                    .application()
+                    .and_then(|app| app.downcast::<crate::application::BleWsGtk>().ok())
+                {
+                    obj.hide();
+                    app.run_in_background();
+                    return Inhibit(true);
+                }
+            }
             Inhibit(false)
         }
     }
@@ -133,7 +154,18 @@ impl Window {
             .connect_clicked(glib::clone!(@strong tx => move |_| {
                 tx.send(Event::OpenOverview).unwrap();
             }));
+        self_
+            .overview_graph_button
+            .connect_clicked(glib::clone!(@strong tx => move |_| {
+                tx.send(Event::OpenComparison).unwrap();
+            }));
+        self_
+            .alerts_button
+            .connect_clicked(glib::clone!(@strong tx => move |_| {
+                tx.send(Event::OpenAlerts).unwrap();
+            }));
         self_.sensor_detail.init(tx.clone());
+        self_.alert_history.init(tx.clone());
 
         let builder = gtk::Builder::from_resource("/li/_5kw/BleWsGtk/shortcuts.ui");
         gtk_macros::get_widget!(builder, gtk::ShortcutsWindow, shortcuts);
@@ -159,6 +191,13 @@ impl Window {
         self_.sensor_detail.set_static_timeseries(timeseries);
     }
 
+    pub fn set_overlay_timeseries(&self, timeseries: Timeseries, shift: u32) {
+        let self_ = imp::Window::from_instance(self);
+        self_
+            .sensor_detail
+            .set_overlay_timeseries(timeseries, shift);
+    }
+
     pub fn switch_view(&self, view: &View) {
         let self_ = imp::Window::from_instance(self);
         match view {
@@ -171,6 +210,7 @@ impl Window {
                 self_.main_stack.set_visible_child(&*self_.sensor_overview);
                 self_.previous_button.set_visible(false);
                 self_.overview_graph_button.set_visible(true);
+                self_.alerts_button.set_visible(true);
                 self_.search_button.set_visible(true);
                 self_.sensor_overview.set_displayed_sensor(None);
             }
@@ -182,15 +222,45 @@ impl Window {
                     .sensor_overview
                     .set_displayed_sensor(Some(sensor.clone()));
             }
-            View::Detail { sensor, .. } => {
+            View::Detail {
+                sensor,
+                unit,
+                range,
+            } => {
                 self.deactivate_search();
                 self_.view_switcher_title.set_view_switcher_enabled(true);
                 self_.main_stack.set_visible_child(&*self_.sensor_detail);
                 self_.previous_button.set_visible(true);
                 self_.overview_graph_button.set_visible(false);
+                self_.alerts_button.set_visible(false);
                 self_.search_button.set_visible(false);
                 self_.sensor_detail.set_sensor(Some(sensor.clone()));
-                self_.sensor_detail.set_live(true);
+                self_.sensor_detail.set_unit(*unit);
+                match range {
+                    Some((from, to)) => self_.sensor_detail.show_range(*from, *to),
+                    None => self_.sensor_detail.set_live(true),
+                }
+            }
+            View::Comparison { sensors } => {
+                self.deactivate_search();
+                self_.view_switcher_title.set_view_switcher_enabled(false);
+                self_
+                    .main_stack
+                    .set_visible_child(&*self_.sensor_comparison);
+                self_.previous_button.set_visible(true);
+                self_.overview_graph_button.set_visible(false);
+                self_.alerts_button.set_visible(false);
+                self_.search_button.set_visible(false);
+                self_.sensor_comparison.set_sensors(sensors);
+            }
+            View::Alerts => {
+                self.deactivate_search();
+                self_.view_switcher_title.set_view_switcher_enabled(false);
+                self_.main_stack.set_visible_child(&*self_.alert_history);
+                self_.previous_button.set_visible(true);
+                self_.overview_graph_button.set_visible(false);
+                self_.alerts_button.set_visible(false);
+                self_.search_button.set_visible(false);
             }
         }
     }
@@ -200,6 +270,31 @@ impl Window {
         self_.sensor_overview.add_sensor(obj);
     }
 
+    pub fn set_alert_history(&self, entries: &[HistoryEntry]) {
+        let self_ = imp::Window::from_instance(self);
+        self_.alert_history.set_entries(entries);
+    }
+
+    pub fn push_alert_history(&self, entry: HistoryEntry) {
+        let self_ = imp::Window::from_instance(self);
+        self_.alert_history.push_entry(entry);
+    }
+
+    /// Redisplay values after the unit preferences changed, sensor rows
+    /// follow the sensors.
+    pub fn refresh_units(&self) {
+        let self_ = imp::Window::from_instance(self);
+        self_.sensor_overview.refresh_units();
+        self_.sensor_detail.refresh_units();
+        self_.sensor_comparison.refresh_units();
+        self_.alert_history.refresh_units();
+    }
+
+    pub fn sensors(&self) -> gio::ListModel {
+        let self_ = imp::Window::from_instance(self);
+        self_.sensor_overview.sensors()
+    }
+
     pub fn save_window_state(&self) -> Result<(), glib::BoolError> {
         let settings = &imp::Window::from_instance(self).settings;
 